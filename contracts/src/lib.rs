@@ -1,7 +1,7 @@
 // User Map Contract - simple key-value storage
 use cosmwasm_std::{
     entry_point, to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
-    StdError,
+    Addr, StdError,
 };
 use cw2::set_contract_version;
 use cw_storage_plus::Map;
@@ -11,8 +11,10 @@ use serde::{Deserialize, Serialize};
 const CONTRACT_NAME: &str = "xion-user-map";
 const CONTRACT_VERSION: &str = "1.0.0";
 
-// Storage - maps user addresses to their JSON data
+// Storage - legacy flat map from before keys were namespaced, read-only fallback
 const USER_DATA: Map<&str, String> = Map::new("user_data");
+// Storage - values namespaced by owner address; the empty key is the owner's profile
+const OWNER_DATA: Map<(&Addr, &str), String> = Map::new("owner_data");
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {}
@@ -29,7 +31,8 @@ pub enum ExecuteMsg {
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
-    GetValue { key: String },
+    // Without an owner this reads a legacy flat key
+    GetValue { key: String, owner: Option<String> },
     GetValueByUser { address: String },
 }
 
@@ -53,12 +56,11 @@ pub fn execute(
 ) -> Result<Response, ContractError> {
     match msg {
         ExecuteMsg::SetValue { key, value } => {
-            let storage_key = if key.is_empty() {
-                info.sender.to_string()
-            } else {
-                key
-            };
-            USER_DATA.save(deps.storage, &storage_key, &value)?;
+            // Callers can only write inside their own namespace
+            OWNER_DATA.save(deps.storage, (&info.sender, &key), &value)?;
+            if key.is_empty() {
+                USER_DATA.remove(deps.storage, info.sender.as_str());
+            }
             Ok(Response::new())
         }
     }
@@ -67,13 +69,19 @@ pub fn execute(
 #[entry_point]
 pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
-        QueryMsg::GetValue { key } => {
-            let value = USER_DATA.may_load(deps.storage, &key)?;
+        QueryMsg::GetValue { key, owner } => {
+            let value = match owner {
+                Some(owner) => {
+                    let addr = deps.api.addr_validate(&owner)?;
+                    load_value(deps, &addr, &key)?
+                }
+                None => USER_DATA.may_load(deps.storage, &key)?,
+            };
             to_json_binary(&value)
         }
         QueryMsg::GetValueByUser { address } => {
             let addr = deps.api.addr_validate(&address)?;
-            let value = USER_DATA.may_load(deps.storage, addr.as_str())?;
+            let value = load_value(deps, &addr, "")?;
             match value {
                 Some(v) => to_json_binary(&v),
                 None => Err(StdError::generic_err("No value found for user")),
@@ -82,6 +90,15 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
     }
 }
 
+fn load_value(deps: Deps, owner: &Addr, key: &str) -> StdResult<Option<String>> {
+    let value = OWNER_DATA.may_load(deps.storage, (owner, key))?;
+    if value.is_none() && key.is_empty() {
+        // Profiles written before namespacing were stored under the owner's address
+        return USER_DATA.may_load(deps.storage, owner.as_str());
+    }
+    Ok(value)
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum ContractError {
     #[error("{0}")]
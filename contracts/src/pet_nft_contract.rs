@@ -1,32 +1,177 @@
 use cosmwasm_std::{
-    entry_point, to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Empty,
+    entry_point, to_json_binary, Addr, BankMsg, Binary, Coin, Deps, DepsMut, Env, MessageInfo,
+    Order, Response, StdResult, Empty, Storage,
 };
 use cw2::set_contract_version;
+use cw_storage_plus::{Bound, Item, Map};
+use cw721::Expiration;
 use cw721_base::{
-    state::Cw721Contract,
-    ContractError,
+    state::{Cw721Contract, TokenInfo},
+    Action,
     InstantiateMsg as Cw721InstantiateMsg,
     QueryMsg as Cw721QueryMsg,
     ExecuteMsg as Cw721ExecuteMsg,
 };
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 const CONTRACT_NAME: &str = "xion-pet-nft";
 const CONTRACT_VERSION: &str = "1.0.0";
+const TOKEN_ID_PREFIX: &str = "pet-";
+// Matches the sprites shipped with the app
+const DEFAULT_SPECIES: [&str; 8] =
+    ["cat", "dog", "fox", "rabbit", "owl", "fish", "bird", "butterfly"];
+const RARITIES: [&str; 4] = ["common", "rare", "epic", "legendary"];
+const DEFAULT_RARITY_WEIGHTS: [u32; 4] = [60, 25, 10, 5];
+// A roll must be revealed within this many blocks of its commit, or the payment is forfeit
+const REVEAL_WINDOW: u64 = 100;
+const DEFAULT_BREEDING_COOLDOWN: u64 = 24 * 60 * 60;
+// Percent chance per breed that the child gets a random species
+const SPECIES_MUTATION_CHANCE: u64 = 5;
+const MAX_LINEAGE_DEPTH: u32 = 5;
+
+// Storage - next sequential number handed out by auto-ID mints
+const NEXT_TOKEN_ID: Item<u64> = Item::new("next_token_id");
+const CONFIG: Item<Config> = Item::new("config");
+// Storage - starter pet token minted for each address that claimed one
+const STARTER_CLAIMS: Map<&Addr, String> = Map::new("starter_claims");
+// Storage - pets bought by each address through BuyPet
+const PURCHASES: Map<&Addr, u32> = Map::new("purchases");
+const SALES: Item<Sales> = Item::new("sales");
+// Storage - paid roll waiting for its reveal, one per address
+const PENDING_ROLLS: Map<&Addr, PendingRoll> = Map::new("pending_rolls");
+// Storage - hash chain advanced once per block by record_block_seed
+const ENTROPY: Item<Binary> = Item::new("entropy");
+// Storage - pool value recorded by the first execute of each block, by height. Only the
+// last REVEAL_WINDOW blocks are kept, since older seeds can no longer settle a roll.
+const BLOCK_SEEDS: Map<u64, Binary> = Map::new("block_seeds");
+// Storage - admin supply caps per (species, rarity) and for the whole collection
+const SUPPLY_CAPS: Map<(&str, &str), u32> = Map::new("supply_caps");
+const COLLECTION_CAP: Item<u64> = Item::new("collection_cap");
+// Storage - lifetime mint counts, burns do not free up supply
+const MINTED: Map<(&str, &str), u32> = Map::new("minted");
+const TOTAL_MINTED: Item<u64> = Item::new("total_minted");
+// Storage - parents and generation of every bred pet
+const LINEAGE: Map<&str, Lineage> = Map::new("lineage");
+// Storage - when each pet last bred, unix seconds
+const LAST_BRED: Map<&str, u64> = Map::new("last_bred");
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
     pub name: String,
     pub symbol: String,
     pub minter: String,
+    pub admin: Option<String>, // defaults to the sender
+    pub species: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Config {
+    pub admin: Addr,
+    pub species: Vec<String>, // offered by starter claims and sales
+    pub mint_price: Option<Coin>, // None disables BuyPet
+    pub max_purchases_per_address: Option<u32>, // None means unlimited
+    pub species_weights: Vec<Weight>, // used by rolls
+    pub rarity_weights: Vec<Weight>,
+    pub breeding_fee: Option<Coin>, // None makes breeding free
+    pub breeding_cooldown: u64, // seconds before a parent can breed again
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Lineage {
+    pub parents: Vec<String>,
+    pub generation: u32, // pets that were not bred are generation 0
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Ancestor {
+    pub token_id: String,
+    pub depth: u32, // 1 for parents, 2 for grandparents, ...
+    pub parents: Vec<String>, // empty when the ancestor was not bred
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LineageResponse {
+    pub token_id: String,
+    pub generation: u32,
+    pub ancestors: Vec<Ancestor>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Weight {
+    pub value: String,
+    pub weight: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BucketSupply {
+    pub species: String,
+    pub rarity: String,
+    pub minted: u32,
+    pub max: Option<u32>, // None when uncapped
+    pub remaining: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SupplyResponse {
+    pub minted: u64,
+    pub max: Option<u64>,
+    pub remaining: Option<u64>,
+    pub buckets: Vec<BucketSupply>, // configured species x rarity tiers
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingRoll {
+    pub commitment: Binary, // sha256 of the secret revealed later
+    pub committed_at: u64,
+    pub entropy: Binary, // pool snapshot taken at commit
+    pub price: Coin, // refunded if the reveal finds nothing left to mint
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingRollResponse {
+    pub address: Addr,
+    pub roll: Option<PendingRoll>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct Sales {
+    pub sold: u64,
+    pub revenue: Vec<Coin>, // sales and breeding fees, per denom
+    pub withdrawn: Vec<Coin>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SalesResponse {
+    pub mint_price: Option<Coin>,
+    pub max_purchases_per_address: Option<u32>,
+    pub sold: u64,
+    pub revenue: Vec<Coin>,
+    pub withdrawn: Vec<Coin>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PurchasesResponse {
+    pub address: Addr,
+    pub purchased: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StarterClaimResponse {
+    pub address: Addr,
+    pub token_id: Option<String>, // None until the address has claimed
+}
+
+// Mirrors cw721-base 0.18 so wallets and marketplaces can talk to pets directly
+#[allow(clippy::large_enum_variant)]
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
+    // Without `token_id` the contract assigns the next `pet-000123` style ID.
+    // Passing one is meant for backfilling existing tokens.
     Mint {
-        token_id: String,
+        token_id: Option<String>,
         owner: String,
         token_uri: Option<String>,
         extension: Option<Metadata>,
@@ -35,21 +180,134 @@ pub enum ExecuteMsg {
         recipient: String,
         token_id: String,
     },
+    // Transfers to a contract and calls its `receive_nft` with `msg`
+    SendNft {
+        contract: String,
+        token_id: String,
+        msg: Binary,
+    },
+    Approve {
+        spender: String,
+        token_id: String,
+        expires: Option<Expiration>,
+    },
+    Revoke {
+        spender: String,
+        token_id: String,
+    },
+    ApproveAll {
+        operator: String,
+        expires: Option<Expiration>,
+    },
+    RevokeAll {
+        operator: String,
+    },
     Burn {
         token_id: String,
     },
+    // Transfers the minter role (cw-ownable)
+    UpdateOwnership(Action),
+    Extension {
+        msg: Empty,
+    },
+    // Free common pet, one per address, species from the configured list
+    ClaimStarterPet {
+        species: String,
+    },
+    // Public sale - `info.funds` must be exactly the configured mint price
+    BuyPet {
+        species: String,
+    },
+    // Paid like BuyPet, but species and rarity are rolled from the weight tables.
+    // `commitment` is sha256(secret); reveal the secret in a later block to mint.
+    // The roll is settled by the first block seed recorded after the commit, so if
+    // nothing touched the contract in between, the first reveal only records a seed
+    // and has to be sent again.
+    CommitRoll {
+        commitment: Binary,
+    },
+    RevealRoll {
+        secret: Binary,
+    },
+    // Admin only. A zero price stops sales, a zero limit removes the per-address cap.
+    UpdateConfig {
+        species: Option<Vec<String>>,
+        mint_price: Option<Coin>,
+        max_purchases_per_address: Option<u32>,
+        species_weights: Option<Vec<Weight>>,
+        rarity_weights: Option<Vec<Weight>>,
+    },
+    // Admin only - `max: None` removes the cap
+    SetSupplyCap {
+        species: String,
+        rarity: String,
+        max: Option<u32>,
+    },
+    SetCollectionCap {
+        max: Option<u64>,
+    },
+    // Mints a child of two pets the sender owns; both parents then cool down
+    Breed {
+        parent_a: String,
+        parent_b: String,
+    },
+    // Admin only. A zero fee makes breeding free.
+    UpdateBreeding {
+        fee: Option<Coin>,
+        cooldown: Option<u64>,
+    },
+    // Admin only - sends sale proceeds, to the admin when `recipient` is omitted
+    Withdraw {
+        amount: Coin,
+        recipient: Option<String>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
     OwnerOf { token_id: String, include_expired: Option<bool> },
+    Approval { token_id: String, spender: String, include_expired: Option<bool> },
+    Approvals { token_id: String, include_expired: Option<bool> },
+    Operator { owner: String, operator: String, include_expired: Option<bool> },
+    AllOperators {
+        owner: String,
+        include_expired: Option<bool>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
     NumTokens {},
     ContractInfo {},
+    // Returns NftInfoResponse<Option<Metadata>>
+    NftInfo { token_id: String },
+    // Returns AllNftInfoResponse<Option<Metadata>>
+    AllNftInfo { token_id: String, include_expired: Option<bool> },
     AllTokens { start_after: Option<String>, limit: Option<u32> },
     Tokens { owner: String, start_after: Option<String>, limit: Option<u32> },
+    Minter {},
+    Ownership {},
+    Extension { msg: Empty },
+    Config {},
+    StarterClaim { address: String },
+    Sales {},
+    Purchases { address: String },
+    PendingRoll { address: String },
+    Supply {},
+    // Ancestors up to `depth` generations back, capped at MAX_LINEAGE_DEPTH
+    Lineage { token_id: String, depth: Option<u32> },
 }
 
+// Returned as response data from Mint
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MintResponse {
+    pub token_id: String,
+}
+
+pub type NftInfoResponse = cw721::NftInfoResponse<Option<Metadata>>;
+pub type AllNftInfoResponse = cw721::AllNftInfoResponse<Option<Metadata>>;
+
+type PetContract<'a> = Cw721Contract<'a, Option<Metadata>, Empty, Empty, Empty>;
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Metadata {
     pub name: String,
@@ -78,20 +336,48 @@ pub struct Attribute {
 #[entry_point]
 pub fn instantiate(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
+    let admin = msg.admin
+        .map(|addr| deps.api.addr_validate(&addr))
+        .transpose()?
+        .unwrap_or_else(|| info.sender.clone());
+    // Stored lowercase so they line up with the trait values record_mint counts
+    let species: Vec<String> = msg.species
+        .map(|species| species.iter().map(|s| s.to_lowercase()).collect())
+        .unwrap_or_else(|| DEFAULT_SPECIES.iter().map(|s| s.to_string()).collect());
+    let species_weights = species
+        .iter()
+        .map(|species| Weight { value: species.clone(), weight: 1 })
+        .collect();
+    let rarity_weights = RARITIES
+        .iter()
+        .zip(DEFAULT_RARITY_WEIGHTS)
+        .map(|(rarity, weight)| Weight { value: rarity.to_string(), weight })
+        .collect();
+    CONFIG.save(deps.storage, &Config {
+        admin,
+        species,
+        mint_price: None,
+        max_purchases_per_address: None,
+        species_weights,
+        rarity_weights,
+        breeding_fee: None,
+        breeding_cooldown: DEFAULT_BREEDING_COOLDOWN,
+    })?;
+
     let instantiate_msg = Cw721InstantiateMsg {
-        name: msg.name,
-        symbol: msg.symbol,
+        name: msg.name.clone(),
+        symbol: msg.symbol.clone(),
         minter: msg.minter,
     };
 
-    let cw721_contract: Cw721Contract<Option<Metadata>, Empty, Empty, Empty> = Cw721Contract::default();
-    cw721_contract.instantiate(deps, _env, info, instantiate_msg)?;
+    let cw721_contract = PetContract::default();
+    cw721_contract.instantiate(deps, env, info, instantiate_msg)?;
 
     Ok(Response::new()
         .add_attribute("method", "instantiate")
@@ -106,93 +392,966 @@ pub fn execute(
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
-    let cw721_contract: Cw721Contract<Option<Metadata>, Empty, Empty, Empty> = Cw721Contract::default();
+    record_block_seed(deps.storage, &env)?;
 
-    match msg {
+    let base_msg = match msg {
         ExecuteMsg::Mint { token_id, owner, token_uri, extension } => {
-            let owner_addr = deps.api.addr_validate(&owner)?;
-            
-            // Validate zkTLS proof if provided in metadata
-            if let Some(ref metadata) = extension {
-                if let Some(ref proof) = metadata.zktls_proof {
-                    if !validate_zktls_proof(proof)? {
-                        return Err(ContractError::InvalidProof {});
-                    }
-                }
+            return execute_mint(deps, env, info, token_id, owner, token_uri, extension);
+        }
+        ExecuteMsg::ClaimStarterPet { species } => {
+            return execute_claim_starter_pet(deps, env, info, species);
+        }
+        ExecuteMsg::BuyPet { species } => return execute_buy_pet(deps, env, info, species),
+        ExecuteMsg::CommitRoll { commitment } => {
+            return execute_commit_roll(deps, env, info, commitment);
+        }
+        ExecuteMsg::RevealRoll { secret } => return execute_reveal_roll(deps, env, info, secret),
+        ExecuteMsg::UpdateConfig {
+            species,
+            mint_price,
+            max_purchases_per_address,
+            species_weights,
+            rarity_weights,
+        } => {
+            return execute_update_config(
+                deps,
+                info,
+                species,
+                mint_price,
+                max_purchases_per_address,
+                species_weights,
+                rarity_weights,
+            );
+        }
+        ExecuteMsg::SetSupplyCap { species, rarity, max } => {
+            return execute_set_supply_cap(deps, info, species, rarity, max);
+        }
+        ExecuteMsg::SetCollectionCap { max } => return execute_set_collection_cap(deps, info, max),
+        ExecuteMsg::Breed { parent_a, parent_b } => {
+            return execute_breed(deps, env, info, parent_a, parent_b);
+        }
+        ExecuteMsg::UpdateBreeding { fee, cooldown } => {
+            return execute_update_breeding(deps, info, fee, cooldown);
+        }
+        ExecuteMsg::Withdraw { amount, recipient } => {
+            return execute_withdraw(deps, info, amount, recipient);
+        }
+        ExecuteMsg::TransferNft { recipient, token_id } => {
+            Cw721ExecuteMsg::TransferNft { recipient, token_id }
+        }
+        ExecuteMsg::SendNft { contract, token_id, msg } => {
+            Cw721ExecuteMsg::SendNft { contract, token_id, msg }
+        }
+        ExecuteMsg::Approve { spender, token_id, expires } => {
+            Cw721ExecuteMsg::Approve { spender, token_id, expires }
+        }
+        ExecuteMsg::Revoke { spender, token_id } => {
+            Cw721ExecuteMsg::Revoke { spender, token_id }
+        }
+        ExecuteMsg::ApproveAll { operator, expires } => {
+            Cw721ExecuteMsg::ApproveAll { operator, expires }
+        }
+        ExecuteMsg::RevokeAll { operator } => Cw721ExecuteMsg::RevokeAll { operator },
+        ExecuteMsg::Burn { token_id } => Cw721ExecuteMsg::Burn { token_id },
+        ExecuteMsg::UpdateOwnership(action) => Cw721ExecuteMsg::UpdateOwnership(action),
+        ExecuteMsg::Extension { msg } => Cw721ExecuteMsg::Extension { msg },
+    };
+
+    // The base response carries the receive_nft callback for SendNft, so it is returned as-is
+    let cw721_contract = PetContract::default();
+    Ok(cw721_contract.execute(deps, env, info, base_msg)?)
+}
+
+fn execute_mint(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token_id: Option<String>,
+    owner: String,
+    token_uri: Option<String>,
+    extension: Option<Metadata>,
+) -> Result<Response, ContractError> {
+    let owner_addr = deps.api.addr_validate(&owner)?;
+
+    // Validate zkTLS proof if provided in metadata
+    if let Some(ref metadata) = extension {
+        if let Some(ref proof) = metadata.zktls_proof {
+            if !validate_zktls_proof(proof)? {
+                return Err(ContractError::InvalidProof {});
             }
-            
-            let mint_msg = cw721_base::ExecuteMsg::Mint {
-                token_id: token_id.clone(),
-                owner: owner_addr.to_string(),
-                token_uri,
-                extension,
-            };
+        }
+    }
+
+    let cw721_contract = PetContract::default();
+    record_mint(deps.storage, &cw721_contract, extension.as_ref())?;
+    let (token_id, auto_id) = match token_id {
+        Some(token_id) => (token_id, false),
+        None => (next_token_id(deps.storage, &cw721_contract)?, true),
+    };
+
+    let mint_msg = Cw721ExecuteMsg::Mint {
+        token_id: token_id.clone(),
+        owner: owner_addr.to_string(),
+        token_uri,
+        extension,
+    };
+    let response = cw721_contract.execute(deps, env, info, mint_msg)?;
+
+    Ok(response
+        .add_attribute("method", "mint")
+        .add_attribute("auto_id", auto_id.to_string())
+        .set_data(to_json_binary(&MintResponse { token_id })?))
+}
 
-            cw721_contract.execute(deps, env, info, mint_msg)?;
+fn execute_claim_starter_pet(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    species: String,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !config.species.contains(&species) {
+        return Err(ContractError::UnknownSpecies { species });
+    }
+    if STARTER_CLAIMS.has(deps.storage, &info.sender) {
+        return Err(ContractError::StarterAlreadyClaimed {});
+    }
+
+    let mut extension = pet_metadata(&species, "common");
+    extension.attributes.push(Attribute {
+        trait_type: "Starter".to_string(),
+        value: "true".to_string(),
+    });
+
+    let cw721_contract = PetContract::default();
+    let token_id = mint_pet(deps.storage, &cw721_contract, &info.sender, extension)?;
+    STARTER_CLAIMS.save(deps.storage, &info.sender, &token_id)?;
+
+    Ok(mint_response(&env, &info.sender, &token_id)
+        .add_attribute("method", "claim_starter_pet")
+        .add_attribute("species", species)
+        .set_data(to_json_binary(&MintResponse { token_id })?))
+}
+
+fn execute_buy_pet(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    species: String,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !config.species.contains(&species) {
+        return Err(ContractError::UnknownSpecies { species });
+    }
+    let price = charge_sale(deps.storage, &config, &info)?;
+
+    let cw721_contract = PetContract::default();
+    let extension = pet_metadata(&species, "common");
+    let token_id = mint_pet(deps.storage, &cw721_contract, &info.sender, extension)?;
 
-            Ok(Response::new()
-                .add_attribute("method", "mint")
-                .add_attribute("token_id", token_id)
-                .add_attribute("owner", owner))
+    Ok(mint_response(&env, &info.sender, &token_id)
+        .add_attribute("method", "buy_pet")
+        .add_attribute("species", species)
+        .add_attribute("price", price.to_string())
+        .set_data(to_json_binary(&MintResponse { token_id })?))
+}
+
+fn execute_commit_roll(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    commitment: Binary,
+) -> Result<Response, ContractError> {
+    if commitment.len() != 32 {
+        return Err(ContractError::InvalidCommitment {});
+    }
+    // An unrevealed roll still inside its window would be lost by overwriting it
+    if let Some(pending) = PENDING_ROLLS.may_load(deps.storage, &info.sender)? {
+        if env.block.height <= pending.committed_at + REVEAL_WINDOW {
+            return Err(ContractError::RollPending {});
         }
-        ExecuteMsg::TransferNft { recipient, token_id } => {
-            let recipient_addr = deps.api.addr_validate(&recipient)?;
-            
-            let transfer_msg = cw721_base::ExecuteMsg::TransferNft {
-                recipient: recipient_addr.to_string(),
-                token_id: token_id.clone(),
-            };
+    }
 
-            cw721_contract.execute(deps, env, info, transfer_msg)?;
+    let config = CONFIG.load(deps.storage)?;
+    // Don't take payment for a roll that could not mint anything
+    if let Some(max) = COLLECTION_CAP.may_load(deps.storage)? {
+        if total_minted(deps.storage, &PetContract::default())? >= max {
+            return Err(ContractError::CollectionCapReached { max });
+        }
+    }
+    if rollable_species(deps.storage, &config)?.is_empty() {
+        return Err(ContractError::SoldOut {});
+    }
+    let price = charge_sale(deps.storage, &config, &info)?;
 
-            Ok(Response::new()
-                .add_attribute("method", "transfer_nft")
-                .add_attribute("token_id", token_id)
-                .add_attribute("recipient", recipient))
+    let entropy = ENTROPY.load(deps.storage)?;
+    PENDING_ROLLS.save(deps.storage, &info.sender, &PendingRoll {
+        commitment,
+        committed_at: env.block.height,
+        entropy,
+        price: price.clone(),
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("method", "commit_roll")
+        .add_attribute("owner", info.sender.to_string())
+        .add_attribute("price", price.to_string())
+        .add_attribute("reveal_before", (env.block.height + REVEAL_WINDOW + 1).to_string()))
+}
+
+fn execute_reveal_roll(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    secret: Binary,
+) -> Result<Response, ContractError> {
+    let pending = PENDING_ROLLS
+        .may_load(deps.storage, &info.sender)?
+        .ok_or(ContractError::NoPendingRoll {})?;
+    // Same-block reveals would let the caller see the block before committing
+    if env.block.height <= pending.committed_at {
+        return Err(ContractError::RevealTooEarly {});
+    }
+    if env.block.height > pending.committed_at + REVEAL_WINDOW {
+        return Err(ContractError::RollExpired {});
+    }
+    if Sha256::digest(secret.as_slice())[..] != pending.commitment[..] {
+        return Err(ContractError::InvalidCommitment {});
+    }
+
+    // The first seed after the commit must come from an earlier block than this one, so
+    // the outcome is fixed before the reveal and does not depend on when it lands
+    let block_seed = BLOCK_SEEDS
+        .range(
+            deps.storage,
+            Some(Bound::exclusive(pending.committed_at)),
+            Some(Bound::exclusive(env.block.height)),
+            Order::Ascending,
+        )
+        .next()
+        .transpose()?;
+    let Some((seeded_at, block_seed)) = block_seed else {
+        // This call recorded the seed, so the next reveal can settle the roll
+        return Ok(Response::new()
+            .add_attribute("method", "reveal_roll")
+            .add_attribute("owner", info.sender.to_string())
+            .add_attribute("status", "seeded"));
+    };
+    PENDING_ROLLS.remove(deps.storage, &info.sender);
+
+    let seed = Sha256::new()
+        .chain_update(secret.as_slice())
+        .chain_update(pending.entropy.as_slice())
+        .chain_update(block_seed.as_slice())
+        .finalize();
+    let config = CONFIG.load(deps.storage)?;
+    let cw721_contract = PetContract::default();
+    // Sold out buckets are dropped from the tables; if the caps filled up since the
+    // commit, the roll is settled with a refund instead of an error that every retry hits
+    let species_weights = rollable_species(deps.storage, &config)?;
+    let collection_full = match COLLECTION_CAP.may_load(deps.storage)? {
+        Some(max) => total_minted(deps.storage, &cw721_contract)? >= max,
+        None => false,
+    };
+    if species_weights.is_empty() || collection_full {
+        refund_sale(deps.storage, &info.sender, &pending.price)?;
+        return Ok(Response::new()
+            .add_message(BankMsg::Send {
+                to_address: info.sender.to_string(),
+                amount: vec![pending.price.clone()],
+            })
+            .add_attribute("method", "reveal_roll")
+            .add_attribute("owner", info.sender.to_string())
+            .add_attribute("status", "refunded")
+            .add_attribute("refund", pending.price.to_string()));
+    }
+    let species = pick_weighted(&species_weights, &seed[0..8])?;
+    let rarity_weights = available_rarities(deps.storage, &config, &species)?;
+    let rarity = pick_weighted(&rarity_weights, &seed[8..16])?;
+
+    let extension = pet_metadata(&species, &rarity);
+    let token_id = mint_pet(deps.storage, &cw721_contract, &info.sender, extension)?;
+
+    Ok(mint_response(&env, &info.sender, &token_id)
+        .add_attribute("method", "reveal_roll")
+        .add_attribute("seeded_at", seeded_at.to_string())
+        .add_attribute("species", species)
+        .add_attribute("rarity", rarity)
+        .set_data(to_json_binary(&MintResponse { token_id })?))
+}
+
+fn execute_set_supply_cap(
+    deps: DepsMut,
+    info: MessageInfo,
+    species: String,
+    rarity: String,
+    max: Option<u32>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    // A cap on an unknown bucket would never match a mint
+    let species = species.to_lowercase();
+    let rarity = rarity.to_lowercase();
+    if !config.species.contains(&species) {
+        return Err(ContractError::UnknownSpecies { species });
+    }
+    if !RARITIES.contains(&rarity.as_str()) {
+        return Err(ContractError::UnknownRarity { rarity });
+    }
+
+    match max {
+        Some(max) => SUPPLY_CAPS.save(deps.storage, (&species, &rarity), &max)?,
+        None => SUPPLY_CAPS.remove(deps.storage, (&species, &rarity)),
+    }
+
+    Ok(Response::new()
+        .add_attribute("method", "set_supply_cap")
+        .add_attribute("species", species)
+        .add_attribute("rarity", rarity)
+        .add_attribute("max", max.map_or("none".to_string(), |max| max.to_string())))
+}
+
+fn execute_set_collection_cap(
+    deps: DepsMut,
+    info: MessageInfo,
+    max: Option<u64>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    match max {
+        Some(max) => COLLECTION_CAP.save(deps.storage, &max)?,
+        None => COLLECTION_CAP.remove(deps.storage),
+    }
+
+    Ok(Response::new()
+        .add_attribute("method", "set_collection_cap")
+        .add_attribute("max", max.map_or("none".to_string(), |max| max.to_string())))
+}
+
+fn execute_breed(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    parent_a: String,
+    parent_b: String,
+) -> Result<Response, ContractError> {
+    if parent_a == parent_b {
+        return Err(ContractError::InvalidParent { token_id: parent_b });
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    if let Some(fee) = &config.breeding_fee {
+        if info.funds != [fee.clone()] {
+            return Err(ContractError::IncorrectPayment { expected: fee.clone() });
         }
-        ExecuteMsg::Burn { token_id } => {
-            let burn_msg = cw721_base::ExecuteMsg::Burn {
-                token_id: token_id.clone(),
-            };
+        let mut sales = SALES.may_load(deps.storage)?.unwrap_or_default();
+        add_coin(&mut sales.revenue, fee);
+        SALES.save(deps.storage, &sales)?;
+    }
 
-            cw721_contract.execute(deps, env, info, burn_msg)?;
+    let cw721_contract = PetContract::default();
+    let now = env.block.time.seconds();
+    let mut traits = vec![];
+    let mut generation = 0;
+    for token_id in [&parent_a, &parent_b] {
+        let token = cw721_contract.tokens.load(deps.storage, token_id)?;
+        if token.owner != info.sender {
+            return Err(ContractError::Unauthorized {});
+        }
+        // Only pets of a configured species and rarity can breed, not furniture or items
+        let is_pet = token
+            .extension
+            .as_ref()
+            .and_then(|metadata| trait_value(metadata, "Category"))
+            .is_some_and(|category| category.eq_ignore_ascii_case("pet"));
+        let parent_traits = token
+            .extension
+            .as_ref()
+            .and_then(pet_traits)
+            .filter(|(species, rarity)| {
+                is_pet && config.species.contains(species) && RARITIES.contains(&rarity.as_str())
+            })
+            .ok_or_else(|| ContractError::InvalidParent { token_id: token_id.clone() })?;
+        traits.push(parent_traits);
 
-            Ok(Response::new()
-                .add_attribute("method", "burn")
-                .add_attribute("token_id", token_id))
+        if let Some(last_bred) = LAST_BRED.may_load(deps.storage, token_id)? {
+            let ready_at = last_bred.saturating_add(config.breeding_cooldown);
+            if now < ready_at {
+                return Err(ContractError::BreedingCooldown {
+                    token_id: token_id.clone(),
+                    ready_at,
+                });
+            }
         }
+        LAST_BRED.save(deps.storage, token_id, &now)?;
+
+        let parent_generation = LINEAGE
+            .may_load(deps.storage, token_id)?
+            .map_or(0, |lineage| lineage.generation);
+        generation = generation.max(parent_generation + 1);
+    }
+
+    // Block-derived randomness the caller can predict, so breeding never raises rarity;
+    // the worst a caller can steer is which parent's traits are inherited
+    let seed = Sha256::new()
+        .chain_update(ENTROPY.load(deps.storage)?.as_slice())
+        .chain_update(info.sender.as_bytes())
+        .chain_update(format!("{}/{}", parent_a, parent_b).as_bytes())
+        .finalize();
+    let inherit_species = &traits[(seed[0] % 2) as usize].0;
+    let inherit_rarity = &traits[(seed[1] % 2) as usize].1;
+
+    let species = if u64::from(seed[2]) * 100 / 256 < SPECIES_MUTATION_CHANCE {
+        pick_weighted(&config.species_weights, &seed[8..16])?
+    } else {
+        inherit_species.clone()
+    };
+    let rarity = inherit_rarity.clone();
+    let mutated = species != *inherit_species;
+
+    let mut extension = pet_metadata(&species, &rarity);
+    extension.attributes.extend([
+        Attribute { trait_type: "Parent A".to_string(), value: parent_a.clone() },
+        Attribute { trait_type: "Parent B".to_string(), value: parent_b.clone() },
+        Attribute { trait_type: "Generation".to_string(), value: generation.to_string() },
+    ]);
+    let token_id = mint_pet(deps.storage, &cw721_contract, &info.sender, extension)?;
+    LINEAGE.save(deps.storage, &token_id, &Lineage {
+        parents: vec![parent_a.clone(), parent_b.clone()],
+        generation,
+    })?;
+
+    Ok(mint_response(&env, &info.sender, &token_id)
+        .add_attribute("method", "breed")
+        .add_attribute("parent_a", parent_a)
+        .add_attribute("parent_b", parent_b)
+        .add_attribute("species", species)
+        .add_attribute("rarity", rarity)
+        .add_attribute("mutated", mutated.to_string())
+        .set_data(to_json_binary(&MintResponse { token_id })?))
+}
+
+fn execute_update_breeding(
+    deps: DepsMut,
+    info: MessageInfo,
+    fee: Option<Coin>,
+    cooldown: Option<u64>,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if let Some(fee) = fee {
+        config.breeding_fee = Some(fee).filter(|fee| !fee.amount.is_zero());
+    }
+
+    if let Some(cooldown) = cooldown {
+        config.breeding_cooldown = cooldown;
     }
+
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "update_breeding")
+        .add_attribute("cooldown", config.breeding_cooldown.to_string()))
+}
+
+fn execute_withdraw(
+    deps: DepsMut,
+    info: MessageInfo,
+    amount: Coin,
+    recipient: Option<String>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let recipient = recipient
+        .map(|addr| deps.api.addr_validate(&addr))
+        .transpose()?
+        .unwrap_or(config.admin);
+
+    let mut sales = SALES.may_load(deps.storage)?.unwrap_or_default();
+    add_coin(&mut sales.withdrawn, &amount);
+    SALES.save(deps.storage, &sales)?;
+
+    // The bank module rejects the send if the contract balance is too low
+    Ok(Response::new()
+        .add_message(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: vec![amount.clone()],
+        })
+        .add_attribute("method", "withdraw")
+        .add_attribute("amount", amount.to_string())
+        .add_attribute("recipient", recipient.to_string()))
+}
+
+fn execute_update_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    species: Option<Vec<String>>,
+    mint_price: Option<Coin>,
+    max_purchases_per_address: Option<u32>,
+    species_weights: Option<Vec<Weight>>,
+    rarity_weights: Option<Vec<Weight>>,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+
+    // Only admin can update config
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if let Some(species) = species {
+        config.species = species.iter().map(|s| s.to_lowercase()).collect();
+    }
+
+    if let Some(mint_price) = mint_price {
+        config.mint_price = Some(mint_price).filter(|price| !price.amount.is_zero());
+    }
+
+    if let Some(limit) = max_purchases_per_address {
+        config.max_purchases_per_address = Some(limit).filter(|limit| *limit > 0);
+    }
+
+    if let Some(species_weights) = species_weights {
+        config.species_weights = species_weights;
+    }
+    // Checked after both updates so a new species list can't leave stale weights behind
+    validate_weights(&config.species_weights, &config.species)?;
+
+    if let Some(rarity_weights) = rarity_weights {
+        let rarities: Vec<String> = RARITIES.iter().map(|r| r.to_string()).collect();
+        validate_weights(&rarity_weights, &rarities)?;
+        config.rarity_weights = rarity_weights;
+    }
+
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "update_config")
+        .add_attribute("species", config.species.join(",")))
 }
 
 #[entry_point]
 pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    let cw721_contract: Cw721Contract<Option<Metadata>, Empty, Empty, Empty> = Cw721Contract::default();
-
-    match msg {
+    let base_msg = match msg {
+        QueryMsg::Config {} => return to_json_binary(&CONFIG.load(deps.storage)?),
+        QueryMsg::StarterClaim { address } => {
+            let address = deps.api.addr_validate(&address)?;
+            return to_json_binary(&StarterClaimResponse {
+                token_id: STARTER_CLAIMS.may_load(deps.storage, &address)?,
+                address,
+            });
+        }
+        QueryMsg::PendingRoll { address } => {
+            let address = deps.api.addr_validate(&address)?;
+            return to_json_binary(&PendingRollResponse {
+                roll: PENDING_ROLLS.may_load(deps.storage, &address)?,
+                address,
+            });
+        }
+        QueryMsg::Supply {} => return to_json_binary(&query_supply(deps)?),
+        QueryMsg::Lineage { token_id, depth } => {
+            return to_json_binary(&query_lineage(deps, token_id, depth)?);
+        }
+        QueryMsg::Sales {} => {
+            let config = CONFIG.load(deps.storage)?;
+            let sales = SALES.may_load(deps.storage)?.unwrap_or_default();
+            return to_json_binary(&SalesResponse {
+                mint_price: config.mint_price,
+                max_purchases_per_address: config.max_purchases_per_address,
+                sold: sales.sold,
+                revenue: sales.revenue,
+                withdrawn: sales.withdrawn,
+            });
+        }
+        QueryMsg::Purchases { address } => {
+            let address = deps.api.addr_validate(&address)?;
+            return to_json_binary(&PurchasesResponse {
+                purchased: PURCHASES.may_load(deps.storage, &address)?.unwrap_or_default(),
+                address,
+            });
+        }
         QueryMsg::OwnerOf { token_id, include_expired } => {
-            let query_msg = Cw721QueryMsg::OwnerOf { token_id, include_expired };
-            to_json_binary(&cw721_contract.query(deps, env, query_msg)?)
+            Cw721QueryMsg::OwnerOf { token_id, include_expired }
+        }
+        QueryMsg::Approval { token_id, spender, include_expired } => {
+            Cw721QueryMsg::Approval { token_id, spender, include_expired }
         }
-        QueryMsg::NumTokens {} => {
-            let query_msg = Cw721QueryMsg::NumTokens {};
-            to_json_binary(&cw721_contract.query(deps, env, query_msg)?)
+        QueryMsg::Approvals { token_id, include_expired } => {
+            Cw721QueryMsg::Approvals { token_id, include_expired }
         }
-        QueryMsg::ContractInfo {} => {
-            let query_msg = Cw721QueryMsg::ContractInfo {};
-            to_json_binary(&cw721_contract.query(deps, env, query_msg)?)
+        QueryMsg::Operator { owner, operator, include_expired } => {
+            Cw721QueryMsg::Operator { owner, operator, include_expired }
+        }
+        QueryMsg::AllOperators { owner, include_expired, start_after, limit } => {
+            Cw721QueryMsg::AllOperators { owner, include_expired, start_after, limit }
+        }
+        QueryMsg::NumTokens {} => Cw721QueryMsg::NumTokens {},
+        QueryMsg::ContractInfo {} => Cw721QueryMsg::ContractInfo {},
+        QueryMsg::NftInfo { token_id } => Cw721QueryMsg::NftInfo { token_id },
+        QueryMsg::AllNftInfo { token_id, include_expired } => {
+            Cw721QueryMsg::AllNftInfo { token_id, include_expired }
         }
         QueryMsg::AllTokens { start_after, limit } => {
-            let query_msg = Cw721QueryMsg::AllTokens { start_after, limit };
-            to_json_binary(&cw721_contract.query(deps, env, query_msg)?)
+            Cw721QueryMsg::AllTokens { start_after, limit }
         }
         QueryMsg::Tokens { owner, start_after, limit } => {
-            let query_msg = Cw721QueryMsg::Tokens { owner, start_after, limit };
-            to_json_binary(&cw721_contract.query(deps, env, query_msg)?)
+            Cw721QueryMsg::Tokens { owner, start_after, limit }
+        }
+        QueryMsg::Minter {} => Cw721QueryMsg::Minter {},
+        QueryMsg::Ownership {} => Cw721QueryMsg::Ownership {},
+        QueryMsg::Extension { msg } => Cw721QueryMsg::Extension { msg },
+    };
+
+    // The base contract already serializes its responses, including the typed extension
+    let cw721_contract = PetContract::default();
+    cw721_contract.query(deps, env, base_msg)
+}
+
+fn query_supply(deps: Deps) -> StdResult<SupplyResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let cw721_contract = PetContract::default();
+    let minted = total_minted(deps.storage, &cw721_contract)?;
+    let max = COLLECTION_CAP.may_load(deps.storage)?;
+
+    let mut buckets = vec![];
+    for species in &config.species {
+        for rarity in RARITIES {
+            let bucket_minted =
+                MINTED.may_load(deps.storage, (species, rarity))?.unwrap_or_default();
+            let bucket_max = SUPPLY_CAPS.may_load(deps.storage, (species, rarity))?;
+            buckets.push(BucketSupply {
+                species: species.clone(),
+                rarity: rarity.to_string(),
+                minted: bucket_minted,
+                max: bucket_max,
+                remaining: bucket_max.map(|max| max.saturating_sub(bucket_minted)),
+            });
         }
     }
+
+    Ok(SupplyResponse {
+        minted,
+        max,
+        remaining: max.map(|max| max.saturating_sub(minted)),
+        buckets,
+    })
+}
+
+fn query_lineage(deps: Deps, token_id: String, depth: Option<u32>) -> StdResult<LineageResponse> {
+    let max_depth = depth.unwrap_or(MAX_LINEAGE_DEPTH).min(MAX_LINEAGE_DEPTH);
+    let lineage = LINEAGE.may_load(deps.storage, &token_id)?;
+    let generation = lineage.as_ref().map_or(0, |lineage| lineage.generation);
+
+    // Breadth first, one generation per pass; shared ancestors are listed once
+    let mut ancestors: Vec<Ancestor> = vec![];
+    let mut current = lineage.map(|lineage| lineage.parents).unwrap_or_default();
+    for level in 1..=max_depth {
+        let mut next = vec![];
+        for ancestor in current {
+            if ancestors.iter().any(|seen| seen.token_id == ancestor) {
+                continue;
+            }
+            let parents = LINEAGE
+                .may_load(deps.storage, &ancestor)?
+                .map(|lineage| lineage.parents)
+                .unwrap_or_default();
+            next.extend(parents.iter().cloned());
+            ancestors.push(Ancestor { token_id: ancestor, depth: level, parents });
+        }
+        current = next;
+    }
+
+    Ok(LineageResponse { token_id, generation, ancestors })
 }
 
 // Helper functions
+// Instances that minted before supply tracking start from the live token count
+fn total_minted(storage: &dyn Storage, cw721_contract: &PetContract) -> StdResult<u64> {
+    match TOTAL_MINTED.may_load(storage)? {
+        Some(total) => Ok(total),
+        None => cw721_contract.token_count(storage),
+    }
+}
+
+// Counts a mint against the collection cap and, for pets naming a known species and
+// rarity, against that bucket's cap. Tokens in another category (furniture, items) only
+// count against the collection cap. Once any cap is set, pets that cannot be placed in a
+// bucket are rejected so they can't slip past the caps.
+fn record_mint(
+    storage: &mut dyn Storage,
+    cw721_contract: &PetContract,
+    extension: Option<&Metadata>,
+) -> Result<(), ContractError> {
+    let total = total_minted(storage, cw721_contract)? + 1;
+    let collection_cap = COLLECTION_CAP.may_load(storage)?;
+    if let Some(max) = collection_cap {
+        if total > max {
+            return Err(ContractError::CollectionCapReached { max });
+        }
+    }
+    TOTAL_MINTED.save(storage, &total)?;
+
+    let category = extension.and_then(|metadata| trait_value(metadata, "Category"));
+    if category.is_some_and(|category| !category.eq_ignore_ascii_case("pet")) {
+        return Ok(());
+    }
+
+    let config = CONFIG.load(storage)?;
+    let traits = extension.and_then(pet_traits).filter(|(species, rarity)| {
+        config.species.contains(species) && RARITIES.contains(&rarity.as_str())
+    });
+    let Some((species, rarity)) = traits else {
+        let claims_pet = category.is_some();
+        if claims_pet && (collection_cap.is_some() || !SUPPLY_CAPS.is_empty(storage)) {
+            return Err(ContractError::UnknownTraits {});
+        }
+        return Ok(());
+    };
+
+    let minted = MINTED.may_load(storage, (&species, &rarity))?.unwrap_or_default() + 1;
+    if let Some(max) = SUPPLY_CAPS.may_load(storage, (&species, &rarity))? {
+        if minted > max {
+            return Err(ContractError::SupplyCapReached { species, rarity, max });
+        }
+    }
+    MINTED.save(storage, (&species, &rarity), &minted)?;
+    Ok(())
+}
+
+// Lowercased species and rarity; minters may name the species trait "Type" or "Species"
+fn pet_traits(metadata: &Metadata) -> Option<(String, String)> {
+    let species = trait_value(metadata, "Type").or_else(|| trait_value(metadata, "Species"))?;
+    let rarity = trait_value(metadata, "Rarity")?;
+    Some((species.to_lowercase(), rarity.to_lowercase()))
+}
+
+fn trait_value<'a>(metadata: &'a Metadata, trait_type: &str) -> Option<&'a str> {
+    metadata
+        .attributes
+        .iter()
+        .find(|attribute| attribute.trait_type.eq_ignore_ascii_case(trait_type))
+        .map(|attribute| attribute.value.as_str())
+}
+
+// Species weights, minus species whose rarity tiers are all sold out
+fn rollable_species(storage: &dyn Storage, config: &Config) -> StdResult<Vec<Weight>> {
+    let mut available = vec![];
+    for entry in &config.species_weights {
+        if !available_rarities(storage, config, &entry.value)?.is_empty() {
+            available.push(entry.clone());
+        }
+    }
+    Ok(available)
+}
+
+// Rarity weights for `species`, minus tiers whose supply cap is used up
+fn available_rarities(
+    storage: &dyn Storage,
+    config: &Config,
+    species: &str,
+) -> StdResult<Vec<Weight>> {
+    let mut available = vec![];
+    for entry in &config.rarity_weights {
+        let key = (species, entry.value.as_str());
+        let sold_out = match SUPPLY_CAPS.may_load(storage, key)? {
+            Some(max) => MINTED.may_load(storage, key)?.unwrap_or_default() >= max,
+            None => false,
+        };
+        if entry.weight > 0 && !sold_out {
+            available.push(entry.clone());
+        }
+    }
+    Ok(available)
+}
+
+// Mints straight into cw721 storage for contract-driven mints that bypass the minter check
+fn mint_pet(
+    storage: &mut dyn Storage,
+    cw721_contract: &PetContract,
+    owner: &Addr,
+    extension: Metadata,
+) -> Result<String, ContractError> {
+    record_mint(storage, cw721_contract, Some(&extension))?;
+    let token_id = next_token_id(storage, cw721_contract)?;
+    let token = TokenInfo {
+        owner: owner.clone(),
+        approvals: vec![],
+        token_uri: None,
+        extension: Some(extension),
+    };
+    cw721_contract.tokens.save(storage, &token_id, &token)?;
+    cw721_contract.increment_tokens(storage)?;
+    Ok(token_id)
+}
+
+// The attributes cw721-base emits on mint, so indexers also see contract-driven mints
+fn mint_response(env: &Env, owner: &Addr, token_id: &str) -> Response {
+    Response::new()
+        .add_attribute("action", "mint")
+        .add_attribute("minter", env.contract.address.to_string())
+        .add_attribute("owner", owner.to_string())
+        .add_attribute("token_id", token_id)
+}
+
+// Same attribute layout the app uses when it builds metadata client-side
+fn pet_metadata(species: &str, rarity: &str) -> Metadata {
+    Metadata {
+        name: format!("{} {}", capitalize(rarity), capitalize(species)),
+        description: format!("A {} {} pet", rarity, species),
+        image: String::new(),
+        attributes: vec![
+            Attribute { trait_type: "Type".to_string(), value: species.to_string() },
+            Attribute { trait_type: "Rarity".to_string(), value: rarity.to_string() },
+            Attribute { trait_type: "Category".to_string(), value: "pet".to_string() },
+        ],
+        zktls_proof: None,
+    }
+}
+
+// Checks the sale is open, the exact price was sent and the buyer is under their limit
+fn charge_sale(
+    storage: &mut dyn Storage,
+    config: &Config,
+    info: &MessageInfo,
+) -> Result<Coin, ContractError> {
+    let price = config.mint_price.clone().ok_or(ContractError::SaleClosed {})?;
+    if info.funds != [price.clone()] {
+        return Err(ContractError::IncorrectPayment { expected: price });
+    }
+
+    let purchased = PURCHASES.may_load(storage, &info.sender)?.unwrap_or_default() + 1;
+    if let Some(limit) = config.max_purchases_per_address {
+        if purchased > limit {
+            return Err(ContractError::PurchaseLimitReached { limit });
+        }
+    }
+    PURCHASES.save(storage, &info.sender, &purchased)?;
+
+    let mut sales = SALES.may_load(storage)?.unwrap_or_default();
+    sales.sold += 1;
+    add_coin(&mut sales.revenue, &price);
+    SALES.save(storage, &sales)?;
+
+    Ok(price)
+}
+
+// Undoes charge_sale's bookkeeping for a paid roll that could not mint
+fn refund_sale(storage: &mut dyn Storage, buyer: &Addr, price: &Coin) -> StdResult<()> {
+    let purchased = PURCHASES.may_load(storage, buyer)?.unwrap_or_default();
+    PURCHASES.save(storage, buyer, &purchased.saturating_sub(1))?;
+
+    let mut sales = SALES.may_load(storage)?.unwrap_or_default();
+    sales.sold = sales.sold.saturating_sub(1);
+    if let Some(revenue) = sales.revenue.iter_mut().find(|coin| coin.denom == price.denom) {
+        revenue.amount = revenue.amount.saturating_sub(price.amount);
+    }
+    SALES.save(storage, &sales)
+}
+
+// Advances the pool once per block from block data only, so callers cannot steer the seeds
+fn record_block_seed(storage: &mut dyn Storage, env: &Env) -> StdResult<()> {
+    let height = env.block.height;
+    if BLOCK_SEEDS.has(storage, height) {
+        return Ok(());
+    }
+
+    let pool = ENTROPY.may_load(storage)?.unwrap_or_default();
+    let seed = Sha256::new()
+        .chain_update(pool.as_slice())
+        .chain_update(height.to_be_bytes())
+        .chain_update(env.block.time.nanos().to_be_bytes())
+        .finalize();
+    let seed = Binary::from(seed.to_vec());
+    ENTROPY.save(storage, &seed)?;
+    BLOCK_SEEDS.save(storage, height, &seed)?;
+
+    // One seed is added per block, so removing a few stale ones keeps the map bounded
+    let cutoff = height.saturating_sub(REVEAL_WINDOW);
+    let stale: Vec<u64> = BLOCK_SEEDS
+        .keys(storage, None, Some(Bound::exclusive(cutoff)), Order::Ascending)
+        .take(4)
+        .collect::<StdResult<_>>()?;
+    for height in stale {
+        BLOCK_SEEDS.remove(storage, height);
+    }
+    Ok(())
+}
+
+fn pick_weighted(table: &[Weight], seed: &[u8]) -> Result<String, ContractError> {
+    let total: u64 = table.iter().map(|entry| entry.weight as u64).sum();
+    if total == 0 {
+        return Err(ContractError::InvalidWeights { reason: "table is empty".to_string() });
+    }
+
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(seed);
+    let mut roll = u64::from_be_bytes(bytes) % total;
+    for entry in table {
+        if roll < entry.weight as u64 {
+            return Ok(entry.value.clone());
+        }
+        roll -= entry.weight as u64;
+    }
+    unreachable!("roll is below the total weight")
+}
+
+fn validate_weights(table: &[Weight], allowed: &[String]) -> Result<(), ContractError> {
+    if table.iter().all(|entry| entry.weight == 0) {
+        return Err(ContractError::InvalidWeights {
+            reason: "at least one weight must be positive".to_string(),
+        });
+    }
+    if let Some(entry) = table.iter().find(|entry| !allowed.contains(&entry.value)) {
+        return Err(ContractError::InvalidWeights {
+            reason: format!("unknown value {}", entry.value),
+        });
+    }
+    Ok(())
+}
+
+fn add_coin(coins: &mut Vec<Coin>, coin: &Coin) {
+    match coins.iter_mut().find(|c| c.denom == coin.denom) {
+        Some(existing) => existing.amount += coin.amount,
+        None => coins.push(coin.clone()),
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn next_token_id(storage: &mut dyn Storage, cw721_contract: &PetContract) -> StdResult<String> {
+    let mut next = NEXT_TOKEN_ID.may_load(storage)?.unwrap_or(1);
+    loop {
+        let token_id = format!("{}{:06}", TOKEN_ID_PREFIX, next);
+        next += 1;
+        // Skip numbers already taken by a backfilled token
+        if cw721_contract.tokens.may_load(storage, &token_id)?.is_none() {
+            NEXT_TOKEN_ID.save(storage, &next)?;
+            return Ok(token_id);
+        }
+    }
+}
+
 fn validate_zktls_proof(proof: &ZkTLSProof) -> Result<bool, ContractError> {
     // Basic validation - in a real implementation, this would verify the cryptographic proof
     if proof.signature.is_empty() || proof.data_hash.is_empty() {
@@ -218,4 +1377,298 @@ pub enum ContractError {
 
     #[error("Invalid zkTLS proof")]
     InvalidProof {},
+
+    #[error("Unauthorized")]
+    Unauthorized {},
+
+    #[error("Unknown species: {species}")]
+    UnknownSpecies { species: String },
+
+    #[error("Starter pet already claimed")]
+    StarterAlreadyClaimed {},
+
+    #[error("Pets are not for sale")]
+    SaleClosed {},
+
+    #[error("Send exactly {expected}")]
+    IncorrectPayment { expected: Coin },
+
+    #[error("Purchase limit of {limit} per address reached")]
+    PurchaseLimitReached { limit: u32 },
+
+    #[error("Unknown rarity: {rarity}")]
+    UnknownRarity { rarity: String },
+
+    #[error("Capped mints need a known species and rarity trait")]
+    UnknownTraits {},
+
+    #[error("Supply cap of {max} reached for {rarity} {species}")]
+    SupplyCapReached { species: String, rarity: String, max: u32 },
+
+    #[error("Collection cap of {max} reached")]
+    CollectionCapReached { max: u64 },
+
+    #[error("Every rollable species and rarity is sold out")]
+    SoldOut {},
+
+    #[error("{token_id} cannot be used for breeding")]
+    InvalidParent { token_id: String },
+
+    #[error("{token_id} can breed again at {ready_at}")]
+    BreedingCooldown { token_id: String, ready_at: u64 },
+
+    #[error("Invalid weights: {reason}")]
+    InvalidWeights { reason: String },
+
+    #[error("Commitment must be the sha256 of the revealed secret")]
+    InvalidCommitment {},
+
+    #[error("A roll is already waiting to be revealed")]
+    RollPending {},
+
+    #[error("No roll to reveal")]
+    NoPendingRoll {},
+
+    #[error("Reveal in a later block than the commit")]
+    RevealTooEarly {},
+
+    #[error("Roll was not revealed in time")]
+    RollExpired {},
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info, MockApi, MockQuerier};
+    use cosmwasm_std::{coins, from_json, CosmosMsg, MemoryStorage, OwnedDeps};
+
+    type TestDeps = OwnedDeps<MemoryStorage, MockApi, MockQuerier>;
+
+    const SECRET: &[u8] = b"a secret only the player knows";
+
+    fn setup() -> TestDeps {
+        let mut deps = mock_dependencies();
+        let msg = InstantiateMsg {
+            name: "Pets".to_string(),
+            symbol: "PET".to_string(),
+            minter: "admin".to_string(),
+            admin: None,
+            species: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+        let msg = ExecuteMsg::UpdateConfig {
+            species: None,
+            mint_price: Some(Coin::new(100, "uxion")),
+            max_purchases_per_address: None,
+            species_weights: None,
+            rarity_weights: None,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+        deps
+    }
+
+    fn env_at(height: u64) -> Env {
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds((height - env.block.height) * 6);
+        env.block.height = height;
+        env
+    }
+
+    fn commit(deps: &mut TestDeps, height: u64) {
+        let commitment = Binary::from(Sha256::digest(SECRET).to_vec());
+        let msg = ExecuteMsg::CommitRoll { commitment };
+        execute(deps.as_mut(), env_at(height), mock_info("player", &coins(100, "uxion")), msg)
+            .unwrap();
+    }
+
+    fn reveal(deps: &mut TestDeps, height: u64) -> Result<Response, ContractError> {
+        let msg = ExecuteMsg::RevealRoll { secret: Binary::from(SECRET) };
+        execute(deps.as_mut(), env_at(height), mock_info("player", &[]), msg)
+    }
+
+    fn attr(response: &Response, key: &str) -> Option<String> {
+        response.attributes.iter().find(|attr| attr.key == key).map(|attr| attr.value.clone())
+    }
+
+    #[test]
+    fn reveal_waits_for_a_seed_from_a_later_block() {
+        let mut deps = setup();
+        let height = mock_env().block.height;
+        commit(&mut deps, height);
+
+        let err = reveal(&mut deps, height).unwrap_err();
+        assert!(matches!(err, ContractError::RevealTooEarly {}));
+
+        // Nothing touched the contract since the commit, so this only records a seed
+        let res = reveal(&mut deps, height + 1).unwrap();
+        assert_eq!(attr(&res, "status").as_deref(), Some("seeded"));
+        assert_eq!(attr(&res, "token_id"), None);
+
+        let res = reveal(&mut deps, height + 2).unwrap();
+        assert_eq!(attr(&res, "seeded_at"), Some((height + 1).to_string()));
+        assert!(attr(&res, "token_id").is_some());
+        let err = reveal(&mut deps, height + 3).unwrap_err();
+        assert!(matches!(err, ContractError::NoPendingRoll {}));
+    }
+
+    #[test]
+    fn reveal_block_does_not_change_the_outcome() {
+        let outcome = |reveal_height: u64| {
+            let mut deps = setup();
+            let height = mock_env().block.height;
+            commit(&mut deps, height);
+            // Someone else's activity seeds the next block
+            let msg = ExecuteMsg::BuyPet { species: "cat".to_string() };
+            let info = mock_info("other", &coins(100, "uxion"));
+            execute(deps.as_mut(), env_at(height + 1), info, msg).unwrap();
+
+            let res = reveal(&mut deps, height + reveal_height).unwrap();
+            (attr(&res, "species").unwrap(), attr(&res, "rarity").unwrap())
+        };
+
+        assert_eq!(outcome(2), outcome(REVEAL_WINDOW));
+    }
+
+    #[test]
+    fn reveal_rejects_wrong_secrets_and_expired_rolls() {
+        let mut deps = setup();
+        let height = mock_env().block.height;
+        commit(&mut deps, height);
+
+        let msg = ExecuteMsg::RevealRoll { secret: Binary::from(b"guess".to_vec()) };
+        let err = execute(deps.as_mut(), env_at(height + 1), mock_info("player", &[]), msg)
+            .unwrap_err();
+        assert!(matches!(err, ContractError::InvalidCommitment {}));
+
+        let err = reveal(&mut deps, height + REVEAL_WINDOW + 1).unwrap_err();
+        assert!(matches!(err, ContractError::RollExpired {}));
+    }
+
+    #[test]
+    fn species_change_revalidates_existing_weights() {
+        let mut deps = setup();
+        let update = |species: Option<Vec<&str>>, weights: Option<Vec<Weight>>| {
+            ExecuteMsg::UpdateConfig {
+                species: species.map(|list| list.iter().map(|s| s.to_string()).collect()),
+                mint_price: None,
+                max_purchases_per_address: None,
+                species_weights: weights,
+                rarity_weights: None,
+            }
+        };
+
+        let msg = update(Some(vec!["cat", "dog"]), None);
+        let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidWeights { .. }));
+
+        let weights = ["cat", "dog"]
+            .iter()
+            .map(|species| Weight { value: species.to_string(), weight: 1 })
+            .collect();
+        let msg = update(Some(vec!["cat", "dog"]), Some(weights));
+        execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+        let config = CONFIG.load(&deps.storage).unwrap();
+        assert_eq!(config.species, vec!["cat", "dog"]);
+        assert_eq!(config.species_weights.len(), 2);
+    }
+
+    fn furniture(kind: &str) -> Metadata {
+        Metadata {
+            name: kind.to_string(),
+            description: String::new(),
+            image: String::new(),
+            attributes: vec![
+                Attribute { trait_type: "Type".to_string(), value: kind.to_string() },
+                Attribute { trait_type: "Category".to_string(), value: "furniture".to_string() },
+            ],
+            zktls_proof: None,
+        }
+    }
+
+    fn mint(deps: &mut TestDeps, owner: &str, item: Metadata) -> Result<String, ContractError> {
+        let msg = ExecuteMsg::Mint {
+            token_id: None,
+            owner: owner.to_string(),
+            token_uri: None,
+            extension: Some(item),
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg)?;
+        Ok(from_json::<MintResponse>(res.data.unwrap()).unwrap().token_id)
+    }
+
+    #[test]
+    fn supply_caps_only_apply_to_pets() {
+        let mut deps = setup();
+        let msg = ExecuteMsg::SetSupplyCap {
+            species: "cat".to_string(),
+            rarity: "legendary".to_string(),
+            max: Some(1),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+
+        mint(&mut deps, "player", furniture("chair")).unwrap();
+        let err = mint(&mut deps, "player", pet_metadata("dragon", "common")).unwrap_err();
+        assert!(matches!(err, ContractError::UnknownTraits {}));
+
+        // Furniture still counts against the collection cap
+        let msg = ExecuteMsg::SetCollectionCap { max: Some(1) };
+        execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+        let err = mint(&mut deps, "player", furniture("table")).unwrap_err();
+        assert!(matches!(err, ContractError::CollectionCapReached { max: 1 }));
+    }
+
+    #[test]
+    fn roll_is_refunded_when_the_cap_fills_before_the_reveal() {
+        let mut deps = setup();
+        let height = mock_env().block.height;
+        commit(&mut deps, height);
+
+        let msg = ExecuteMsg::SetCollectionCap { max: Some(0) };
+        execute(deps.as_mut(), env_at(height + 1), mock_info("admin", &[]), msg).unwrap();
+
+        let res = reveal(&mut deps, height + 2).unwrap();
+        assert_eq!(attr(&res, "status").as_deref(), Some("refunded"));
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "player".to_string(),
+                amount: coins(100, "uxion"),
+            })
+        );
+        assert!(!PENDING_ROLLS.has(&deps.storage, &Addr::unchecked("player")));
+        let sales = SALES.load(&deps.storage).unwrap();
+        assert_eq!((sales.sold, sales.revenue), (0, coins(0, "uxion")));
+
+        // With the cap reached, new rolls are refused before payment is taken
+        let commitment = Binary::from(Sha256::digest(SECRET).to_vec());
+        let msg = ExecuteMsg::CommitRoll { commitment };
+        let info = mock_info("player", &coins(100, "uxion"));
+        let err = execute(deps.as_mut(), env_at(height + 3), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::CollectionCapReached { max: 0 }));
+    }
+
+    #[test]
+    fn only_configured_pets_can_breed() {
+        let mut deps = setup();
+        let breed = |deps: &mut TestDeps, parent_a: &str, parent_b: &str| {
+            let msg = ExecuteMsg::Breed {
+                parent_a: parent_a.to_string(),
+                parent_b: parent_b.to_string(),
+            };
+            execute(deps.as_mut(), mock_env(), mock_info("player", &[]), msg)
+        };
+
+        let chair = mint(&mut deps, "player", furniture("chair")).unwrap();
+        let table = mint(&mut deps, "player", furniture("table")).unwrap();
+        let err = breed(&mut deps, &chair, &table).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidParent { token_id } if token_id == chair));
+
+        let cat = mint(&mut deps, "player", pet_metadata("cat", "common")).unwrap();
+        let dragon = mint(&mut deps, "player", pet_metadata("dragon", "common")).unwrap();
+        let err = breed(&mut deps, &dragon, &cat).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidParent { token_id } if token_id == dragon));
+
+        let dog = mint(&mut deps, "player", pet_metadata("dog", "rare")).unwrap();
+        breed(&mut deps, &cat, &dog).unwrap();
+    }
 }
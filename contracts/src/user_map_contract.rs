@@ -1,134 +1,2423 @@
 use cosmwasm_std::{
-    entry_point, to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
-    Addr, StdError,
+    entry_point, from_json, to_json_binary, to_json_string, to_json_vec, Binary, Deps, DepsMut,
+    Env, MessageInfo, Reply, Response, StdResult, Addr, BankMsg, CanonicalAddr, Coin, StdError,
+    Order, Storage, SubMsg, SubMsgResult, Uint128, VerificationError, WasmMsg,
 };
-use cw2::set_contract_version;
-use cw_storage_plus::Map;
+use cw2::{get_contract_version, set_contract_version};
+use cw_storage_plus::{Bound, Item, Map};
 use schemars::JsonSchema;
+use ripemd::Ripemd160;
+use semver::Version;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 const CONTRACT_NAME: &str = "xion-user-map";
-const CONTRACT_VERSION: &str = "1.0.0";
+// 1.0.0 is shared with the original lib.rs contract, which migrates to this one
+const CONTRACT_VERSION: &str = "1.1.0";
 
-// Storage - maps user addresses to their JSON data
+// Pagination defaults, same as cw721-base uses for AllTokens
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 100;
+// Entries GetAllValues may read per page when it is not narrowed to one owner
+const MAX_SCAN: usize = 1_000;
+const DEFAULT_MAX_KEY_LENGTH: u32 = 128;
+const DEFAULT_MAX_VALUE_SIZE: u32 = 16 * 1024;
+const DEFAULT_MAX_KEYS_PER_OWNER: u32 = 64;
+const DEFAULT_MAX_HISTORY: u32 = 10;
+const MAX_BATCH_OPS: usize = 32;
+const MAX_IMPORT_ENTRIES: usize = 100;
+const MIN_USERNAME_LENGTH: usize = 3;
+const MAX_USERNAME_LENGTH: usize = 20;
+const MAX_HOOKS: usize = 10;
+// Caps each hook call so an out-of-gas subscriber fails alone instead of aborting the write
+const HOOK_GAS_LIMIT: u64 = 500_000;
+const HOOK_REPLY_ID: u64 = 1;
+
+// Player profile schema, matches the pet types and rarities the app renders
+const PET_TYPES: [&str; 8] = ["cat", "dog", "fox", "rabbit", "owl", "fish", "bird", "butterfly"];
+const PET_RARITIES: [&str; 4] = ["common", "rare", "epic", "legendary"];
+const MAX_PROFILE_PETS: usize = 100;
+const MAX_PROFILE_FIELD_LENGTH: usize = 64;
+
+// Storage - legacy flat map from before keys were namespaced, read-only fallback
 const USER_DATA: Map<&str, String> = Map::new("user_data");
+// Storage - values namespaced by owner address; the empty key is the owner's profile
+const OWNER_DATA: Map<(&Addr, &str), String> = Map::new("owner_data");
+const CONFIG: Item<Config> = Item::new("config");
+const USAGE: Map<&Addr, Usage> = Map::new("usage");
+// Storage - storage deposit currently locked for each owner
+const DEPOSITS: Map<&Addr, Uint128> = Map::new("deposits");
+// Sum of DEPOSITS, so the deposit denom can't change under locked funds
+const TOTAL_DEPOSITS: Item<Uint128> = Item::new("total_deposits");
+// Storage - latest version per key, kept after deletes so versions never repeat
+const ENTRY_VERSIONS: Map<(&Addr, &str), EntryVersion> = Map::new("entry_versions");
+const ENTRY_HISTORY: Map<(&Addr, &str, u64), ValueVersion> = Map::new("entry_history");
+// Storage - expiry for entries written with a TTL
+const EXPIRIES: Map<(&Addr, &str), Expiry> = Map::new("expiries");
+const PRUNED_COUNT: Item<u64> = Item::new("pruned_count");
+// Storage - usernames keyed by their lowercase form, plus the reverse lookup
+const USERNAMES: Map<&str, UsernameRecord> = Map::new("usernames");
+const USERNAME_OF: Map<&Addr, String> = Map::new("username_of");
+// Storage - friendships are stored in both directions; requests keyed by (to, from)
+const FRIENDS: Map<(&Addr, &Addr), u64> = Map::new("friends");
+const FRIEND_REQUESTS: Map<(&Addr, &Addr), u64> = Map::new("friend_requests");
+const BLOCKS: Map<(&Addr, &Addr), u64> = Map::new("blocks");
+// Storage - write grants keyed by (owner, grantee)
+const GRANTS: Map<(&Addr, &Addr), Grant> = Map::new("grants");
+// Storage - next nonce each owner must sign with for SetValueSigned
+const NONCES: Map<&Addr, u64> = Map::new("nonces");
+// Storage - contracts notified of owner writes, with the height they were added at
+const HOOKS: Map<&Addr, u64> = Map::new("hooks");
+const ADMIN_LOG: Map<u64, AdminAction> = Map::new("admin_log");
+const ADMIN_LOG_COUNT: Item<u64> = Item::new("admin_log_count");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Config {
+    pub admin: Addr,
+    pub pending_admin: Option<Addr>,
+    pub paused: bool, // blocks owner writes, admin operations still go through
+    pub limits: Limits,
+    #[serde(default)]
+    pub deposit: DepositConfig,
+}
+
+// Owners keep `price_per_byte` locked for every stored byte; a zero price disables deposits
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DepositConfig {
+    pub denom: String,
+    pub price_per_byte: Uint128,
+}
+
+impl Default for DepositConfig {
+    fn default() -> Self {
+        DepositConfig {
+            denom: "uxion".to_string(),
+            price_per_byte: Uint128::zero(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct Limits {
+    pub max_key_length: u32,
+    pub max_value_size: u32, // bytes per entry
+    pub max_keys_per_owner: u32,
+    pub max_history: u32, // versions kept per key for RevertValue
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_key_length: DEFAULT_MAX_KEY_LENGTH,
+            max_value_size: DEFAULT_MAX_VALUE_SIZE,
+            max_keys_per_owner: DEFAULT_MAX_KEYS_PER_OWNER,
+            max_history: DEFAULT_MAX_HISTORY,
+        }
+    }
+}
+
+// Entries written before usage tracking are not counted
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct Usage {
+    pub keys: u32,
+    pub bytes: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EntryVersion {
+    pub version: u64,
+    pub block_height: u64,
+    pub writer: Addr,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ValueVersion {
+    pub version: u64,
+    pub value: Option<String>, // None when this version deleted the key
+    pub block_height: u64,
+    pub writer: Addr,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DepositResponse {
+    pub address: Addr,
+    pub denom: String,
+    pub locked: Uint128,
+    pub required: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NonceResponse {
+    pub address: Addr,
+    pub nonce: u64,
+}
+
+// Canonical payload for SetValueSigned - fields serialize in this order
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SignedWrite {
+    pub chain_id: String,
+    pub contract: String,
+    pub key: String,
+    pub value: String,
+    pub nonce: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UsageResponse {
+    pub address: Addr,
+    pub keys: u32,
+    pub bytes: u64,
+    pub max_keys: u32,
+    pub max_value_size: u32,
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
     pub admin: Option<String>,
+    pub limits: Option<Limits>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrateMsg {
+    // Required when the old instance has no stored config, e.g. one deployed from lib.rs
+    pub admin: Option<String>,
+    pub limits: Option<Limits>,
+    // How many legacy entries to scan for address-keyed profiles to move into owner namespaces
+    pub legacy_profiles_limit: Option<u32>,
+    // Resume the scan after this legacy key, as reported by the previous migration
+    pub legacy_start_after: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
+    // `expected_version` rejects the write if the key changed since it was read.
+    // `owner` writes into another namespace and needs a grant from that owner.
+    // Without a `ttl` any earlier expiry on the key is cleared.
     SetValue {
         key: String,
         value: String,
+        expected_version: Option<u64>,
+        owner: Option<String>,
+        ttl: Option<Ttl>,
     },
     DeleteValue {
         key: String,
+        expected_version: Option<u64>,
+        owner: Option<String>,
+    },
+    // Relayed write - `signature` is a secp256k1 signature by `pubkey` (33 byte compressed)
+    // over sha256 of the SignedWrite JSON. The owner is the address derived from `pubkey`.
+    SetValueSigned {
+        key: String,
+        value: String,
+        nonce: u64,
+        pubkey: Binary,
+        signature: Binary,
+    },
+    // Lets `grantee` write the listed keys and key prefixes until `expires_at` (unix seconds)
+    GrantWrite {
+        grantee: String,
+        keys: Vec<String>,
+        prefixes: Vec<String>,
+        expires_at: Option<u64>,
+    },
+    RevokeWrite {
+        grantee: String,
+    },
+    // Admin override - writes into another owner's namespace and is logged
+    AdminSetValue {
+        owner: String,
+        key: String,
+        value: String,
+    },
+    AdminDeleteValue {
+        owner: String,
+        key: String,
+    },
+    // Admin only - moves a legacy flat entry into an owner's namespace
+    MigrateLegacyValue {
+        legacy_key: String,
+        owner: String,
+        key: String,
+    },
+    // Admin only - bulk load, at most MAX_IMPORT_ENTRIES per message.
+    // Entries returned by the ExportEntries query can be passed back in as-is.
+    ImportEntries {
+        entries: Vec<ImportEntry>,
+        skip_existing: bool,
+    },
+    // Moderation - removes an abusive value and logs the reason
+    TakedownValue {
+        owner: String,
+        key: String,
+        reason: String,
+    },
+    UpdateConfig {
+        paused: Option<bool>,
+        limits: Option<Limits>,
+        deposit: Option<DepositConfig>,
+    },
+    // Two-step admin transfer - the proposed admin has to accept
+    ProposeAdmin {
+        admin: String,
+    },
+    AcceptAdmin {},
+    // Admin only - registered contracts receive a HookMsg after every owner write
+    AddHook {
+        address: String,
+    },
+    RemoveHook {
+        address: String,
+    },
+    // Validated player profile, stored under the caller's empty key
+    SetProfile {
+        profile: PlayerProfile,
+        expected_version: Option<u64>,
+    },
+    // Applies an RFC 7386 JSON merge patch to one of the caller's object values
+    PatchValue {
+        key: String,
+        patch: String,
+        expected_version: Option<u64>,
+    },
+    // Applies every op or none of them
+    Batch {
+        ops: Vec<SetOrDelete>,
+    },
+    // Usernames are unique ignoring case; an address holds at most one
+    ClaimUsername {
+        username: String,
+    },
+    ReleaseUsername {},
+    TransferUsername {
+        recipient: String,
+    },
+    // Sending a request to someone who already requested you accepts theirs
+    SendFriendRequest {
+        to: String,
+    },
+    AcceptFriendRequest {
+        from: String,
+    },
+    RejectFriendRequest {
+        from: String,
+    },
+    RemoveFriend {
+        friend: String,
+    },
+    // Blocking also drops any friendship and pending requests between the two
+    BlockUser {
+        address: String,
+    },
+    UnblockUser {
+        address: String,
+    },
+    // Permissionless - deletes up to `limit` expired entries after `start_after`
+    PruneExpired {
+        start_after: Option<ValueCursor>,
+        limit: Option<u32>,
+    },
+    // Restores one of the caller's earlier versions as a new version
+    RevertValue {
+        key: String,
+        version: u64,
     },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
-    GetValue { key: String },
+    Config {},
+    // Without an owner this reads a legacy flat key
+    GetValue { key: String, owner: Option<String> },
     GetValueByUser { address: String },
-    GetAllValues {},
+    GetProfile { address: String },
+    ResolveUsername { username: String },
+    UsernameOf { address: String },
+    Friends { address: String, start_after: Option<String>, limit: Option<u32> },
+    // Incoming requests
+    PendingFriendRequests { address: String, start_after: Option<String>, limit: Option<u32> },
+    MutualFriends {
+        address: String,
+        other: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    Usage { address: String },
+    Nonce { address: String },
+    Deposit { address: String },
+    // Total number of entries removed by PruneExpired
+    PrunedCount {},
+    // Unexpired grants given by `owner`
+    Grants { owner: String, start_after: Option<String>, limit: Option<u32> },
+    // Newest first
+    ValueHistory { owner: String, key: String, limit: Option<u32> },
+    // Paginated; without `owner` a page may come back short with `next` set,
+    // since at most MAX_SCAN entries are read per call
+    GetAllValues {
+        owner: Option<String>,
+        key_prefix: Option<String>,
+        start_after: Option<ValueCursor>,
+        limit: Option<u32>,
+    },
+    AdminLog { start_after: Option<u64>, limit: Option<u32> },
+    // Every unexpired entry with its expiry and version metadata, for snapshots
+    ExportEntries { start_after: Option<ValueCursor>, limit: Option<u32> },
+    Hooks {},
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerProfile {
+    pub has_starter_pet: bool,
+    pub starter_pet: Option<ProfilePet>,
+    pub pets: Vec<ProfilePetEntry>,
+    // Free-form layout data owned by the app
+    pub house_data: Option<serde_json::Value>,
+    pub last_updated: Option<String>,
+}
+
+// The app lists pets by id; full pet objects are accepted too
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(untagged)]
+pub enum ProfilePetEntry {
+    Id(String),
+    Pet(ProfilePet),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfilePet {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub pet_type: String,
+    pub rarity: String,
+    pub stats: Option<ProfilePetStats>,
+    pub claimed_at: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfilePetStats {
+    pub happiness: u32,
+    pub energy: u32,
+    pub hunger: u32,
+    pub strength: u32,
+    pub agility: u32,
+    pub intelligence: u32,
+    // Millisecond timestamps written by the app
+    pub last_fed: Option<u64>,
+    pub last_played: Option<u64>,
+    pub last_updated: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SetOrDelete {
+    Set {
+        key: String,
+        value: String,
+        expected_version: Option<u64>,
+        ttl: Option<Ttl>,
+    },
+    Delete {
+        key: String,
+        expected_version: Option<u64>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Ttl {
+    Seconds(u64),
+    Blocks(u64),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Expiry {
+    AtTime(u64), // unix seconds
+    AtHeight(u64),
+}
+
+impl Expiry {
+    fn is_expired(&self, env: &Env) -> bool {
+        match self {
+            Expiry::AtTime(time) => env.block.time.seconds() >= *time,
+            Expiry::AtHeight(height) => env.block.height >= *height,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Grant {
+    pub grantee: Addr,
+    pub keys: Vec<String>,
+    pub prefixes: Vec<String>,
+    pub expires_at: Option<u64>,
+}
+
+impl Grant {
+    fn allows(&self, key: &str, now: u64) -> bool {
+        if self.expires_at.is_some_and(|expires_at| now >= expires_at) {
+            return false;
+        }
+        self.keys.iter().any(|k| k == key) || self.prefixes.iter().any(|p| key.starts_with(p))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ValueResponse {
+    pub value: Option<String>,
+    // 0 for keys that were never written and for legacy flat keys
+    pub version: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ValueEntry {
+    pub owner: Addr,
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UsernameRecord {
+    pub username: String, // as claimed, case preserved
+    pub owner: Addr,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FriendRequest {
+    pub from: Addr,
+    pub sent_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PrunedCountResponse {
+    pub pruned: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HooksResponse {
+    pub hooks: Vec<Addr>,
+}
+
+// Sent to hook contracts; hashes are sha256 of the value, None when the key is absent
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HookMsg {
+    UserMapChanged {
+        owner: String,
+        key: String,
+        old_hash: Option<Binary>,
+        new_hash: Option<Binary>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ValueCursor {
+    pub owner: String,
+    pub key: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ImportEntry {
+    pub owner: String,
+    pub key: String,
+    pub value: String,
+    pub expiry: Option<Expiry>,
+    // Version metadata from ExportEntries; without it the key restarts its numbering here
+    #[serde(default)]
+    pub version: Option<EntryVersion>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ExportEntry {
+    pub owner: Addr,
+    pub key: String,
+    pub value: String,
+    pub expiry: Option<Expiry>,
+    // None for entries written by the original lib.rs contract
+    pub version: Option<EntryVersion>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ExportResponse {
+    pub entries: Vec<ExportEntry>,
+    pub next: Option<ValueCursor>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AllValuesResponse {
+    pub values: Vec<ValueEntry>,
+    // Pass back as `start_after` to fetch the next page; None on the last page
+    pub next: Option<ValueCursor>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AdminAction {
+    pub id: u64,
+    pub admin: Addr,
+    pub action: String, // "set_value", "delete_value", "migrate_legacy_value", "takedown"
+    pub owner: Addr,
+    pub key: String,
+    pub reason: Option<String>,
+    pub block_height: u64,
 }
 
 #[entry_point]
 pub fn instantiate(
     deps: DepsMut,
     _env: Env,
-    _info: MessageInfo,
-    _msg: InstantiateMsg,
+    info: MessageInfo,
+    msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
+    let admin = msg.admin
+        .map(|addr| deps.api.addr_validate(&addr))
+        .transpose()?
+        .unwrap_or(info.sender);
+
+    let config = Config {
+        admin,
+        pending_admin: None,
+        paused: false,
+        limits: msg.limits.unwrap_or_default(),
+        deposit: DepositConfig::default(),
+    };
+
+    CONFIG.save(deps.storage, &config)?;
+
     Ok(Response::new()
         .add_attribute("method", "instantiate")
-        .add_attribute("contract", CONTRACT_NAME))
+        .add_attribute("contract", CONTRACT_NAME)
+        .add_attribute("admin", config.admin.to_string()))
+}
+
+#[entry_point]
+pub fn migrate(mut deps: DepsMut, env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+    let stored = get_contract_version(deps.storage)?;
+    if stored.contract != CONTRACT_NAME {
+        return Err(ContractError::WrongContract {
+            expected: CONTRACT_NAME.to_string(),
+            actual: stored.contract,
+        });
+    }
+
+    let stored_version: Version = stored
+        .version
+        .parse()
+        .map_err(|_| StdError::generic_err("Invalid stored contract version"))?;
+    let new_version: Version = CONTRACT_VERSION
+        .parse()
+        .map_err(|_| StdError::generic_err("Invalid contract version"))?;
+    if stored_version > new_version {
+        return Err(ContractError::CannotDowngrade {
+            from: stored.version,
+            to: CONTRACT_VERSION.to_string(),
+        });
+    }
+
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    let mut config = match CONFIG.may_load(deps.storage)? {
+        Some(config) => config,
+        None => {
+            let admin = msg
+                .admin
+                .ok_or_else(|| StdError::generic_err("admin is required for this migration"))?;
+            Config {
+                admin: deps.api.addr_validate(&admin)?,
+                pending_admin: None,
+                paused: false,
+                limits: Limits::default(),
+                deposit: DepositConfig::default(),
+            }
+        }
+    };
+    if let Some(limits) = msg.limits {
+        config.limits = limits;
+    }
+    CONFIG.save(deps.storage, &config)?;
+
+    // lib.rs originally stored profiles flat under the owner's address
+    let limit = msg.legacy_profiles_limit.unwrap_or_default() as usize;
+    let start = msg.legacy_start_after.as_deref().map(Bound::exclusive);
+    let legacy: Vec<(String, String)> = USER_DATA
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<_>>()?;
+    let last_key = if legacy.len() == limit {
+        legacy.last().map(|(key, _)| key.clone())
+    } else {
+        None
+    };
+
+    let contract = env.contract.address.clone();
+    let mut moved = 0u32;
+    // Entries that could not be moved stay in place for MigrateLegacyValue
+    let mut skipped = vec![];
+    for (legacy_key, value) in legacy {
+        let Ok(owner) = deps.api.addr_validate(&legacy_key) else {
+            skipped.push(legacy_key);
+            continue;
+        };
+        if OWNER_DATA.has(deps.storage, (&owner, "")) {
+            // Already superseded by a namespaced profile
+            USER_DATA.remove(deps.storage, owner.as_str());
+            continue;
+        }
+        match save_value(deps.branch(), &env, &contract, &owner, "", &value) {
+            Ok(_) => {
+                set_expiry(deps.branch(), &env, &owner, "", None)?;
+                moved += 1;
+            }
+            // Oversize values must not abort the rest of the upgrade
+            Err(ContractError::QuotaExceeded { .. }) => skipped.push(legacy_key),
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(Response::new()
+        .add_attribute("method", "migrate")
+        .add_attribute("from_version", stored_version.to_string())
+        .add_attribute("to_version", CONTRACT_VERSION)
+        .add_attribute("legacy_profiles_moved", moved.to_string())
+        .add_attribute("legacy_profiles_skipped", skipped.join(","))
+        .add_attribute("legacy_last_key", last_key.unwrap_or_default()))
 }
 
 #[entry_point]
 pub fn execute(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     match msg {
-        ExecuteMsg::SetValue { key, value } => execute_set_value(deps, info, key, value),
-        ExecuteMsg::DeleteValue { key } => execute_delete_value(deps, info, key),
+        ExecuteMsg::SetValue { key, value, expected_version, owner, ttl } => {
+            execute_set_value(deps, env, info, owner, key, value, expected_version, ttl)
+        }
+        ExecuteMsg::DeleteValue { key, expected_version, owner } => {
+            execute_delete_value(deps, env, info, owner, key, expected_version)
+        }
+        ExecuteMsg::SetValueSigned { key, value, nonce, pubkey, signature } => {
+            execute_set_value_signed(deps, env, info, key, value, nonce, pubkey, signature)
+        }
+        ExecuteMsg::GrantWrite { grantee, keys, prefixes, expires_at } => {
+            execute_grant_write(deps, env, info, grantee, keys, prefixes, expires_at)
+        }
+        ExecuteMsg::RevokeWrite { grantee } => execute_revoke_write(deps, info, grantee),
+        ExecuteMsg::AdminSetValue { owner, key, value } => {
+            execute_admin_set_value(deps, env, info, owner, key, value)
+        }
+        ExecuteMsg::AdminDeleteValue { owner, key } => {
+            execute_admin_delete_value(deps, env, info, owner, key)
+        }
+        ExecuteMsg::MigrateLegacyValue { legacy_key, owner, key } => {
+            execute_migrate_legacy_value(deps, env, info, legacy_key, owner, key)
+        }
+        ExecuteMsg::ImportEntries { entries, skip_existing } => {
+            execute_import_entries(deps, env, info, entries, skip_existing)
+        }
+        ExecuteMsg::TakedownValue { owner, key, reason } => {
+            execute_takedown_value(deps, env, info, owner, key, reason)
+        }
+        ExecuteMsg::UpdateConfig { paused, limits, deposit } => {
+            execute_update_config(deps, info, paused, limits, deposit)
+        }
+        ExecuteMsg::ProposeAdmin { admin } => execute_propose_admin(deps, info, admin),
+        ExecuteMsg::AcceptAdmin {} => execute_accept_admin(deps, info),
+        ExecuteMsg::AddHook { address } => execute_add_hook(deps, env, info, address),
+        ExecuteMsg::RemoveHook { address } => execute_remove_hook(deps, info, address),
+        ExecuteMsg::SetProfile { profile, expected_version } => {
+            execute_set_profile(deps, env, info, profile, expected_version)
+        }
+        ExecuteMsg::ClaimUsername { username } => execute_claim_username(deps, info, username),
+        ExecuteMsg::ReleaseUsername {} => execute_release_username(deps, info),
+        ExecuteMsg::TransferUsername { recipient } => {
+            execute_transfer_username(deps, info, recipient)
+        }
+        ExecuteMsg::SendFriendRequest { to } => execute_send_friend_request(deps, env, info, to),
+        ExecuteMsg::AcceptFriendRequest { from } => {
+            execute_accept_friend_request(deps, env, info, from)
+        }
+        ExecuteMsg::RejectFriendRequest { from } => {
+            execute_reject_friend_request(deps, info, from)
+        }
+        ExecuteMsg::RemoveFriend { friend } => execute_remove_friend(deps, info, friend),
+        ExecuteMsg::BlockUser { address } => execute_block_user(deps, env, info, address),
+        ExecuteMsg::UnblockUser { address } => execute_unblock_user(deps, info, address),
+        ExecuteMsg::PatchValue { key, patch, expected_version } => {
+            execute_patch_value(deps, env, info, key, patch, expected_version)
+        }
+        ExecuteMsg::Batch { ops } => execute_batch(deps, env, info, ops),
+        ExecuteMsg::PruneExpired { start_after, limit } => {
+            execute_prune_expired(deps, env, info, start_after, limit)
+        }
+        ExecuteMsg::RevertValue { key, version } => {
+            execute_revert_value(deps, env, info, key, version)
+        }
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn execute_set_value(
-    deps: DepsMut,
+    mut deps: DepsMut,
+    env: Env,
     info: MessageInfo,
+    owner: Option<String>,
     key: String,
     value: String,
+    expected_version: Option<u64>,
+    ttl: Option<Ttl>,
 ) -> Result<Response, ContractError> {
-    // Allow users to set their own data or use a specific key
-    let storage_key = if key.is_empty() {
-        info.sender.to_string()
-    } else {
-        key
-    };
+    ensure_not_paused(deps.as_ref())?;
+    let owner = resolve_owner(deps.as_ref(), &env, &info, owner, &key)?;
+    ensure_version(deps.as_ref(), &owner, &key, expected_version)?;
 
-    USER_DATA.save(deps.storage, &storage_key, &value)?;
+    let old = load_value(deps.as_ref(), &env, &owner, &key)?;
+    let bytes_before = stored_bytes(deps.as_ref(), &owner)?;
+    let version = save_value(deps.branch(), &env, &info.sender, &owner, &key, &value)?;
+    set_expiry(deps.branch(), &env, &owner, &key, ttl)?;
+    let hooks = hook_messages(deps.as_ref(), &owner, &key, old.as_deref(), Some(&value))?;
+    let refund = settle_deposit(deps, &owner, &info.funds, Some(bytes_before))?;
 
     Ok(Response::new()
+        .add_messages(refund)
+        .add_submessages(hooks)
         .add_attribute("method", "set_value")
-        .add_attribute("key", storage_key)
+        .add_attribute("owner", owner.to_string())
+        .add_attribute("key", key)
+        .add_attribute("version", version.to_string())
         .add_attribute("sender", info.sender.to_string()))
 }
 
-fn execute_delete_value(
-    deps: DepsMut,
+#[allow(clippy::too_many_arguments)]
+fn execute_set_value_signed(
+    mut deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     key: String,
+    value: String,
+    nonce: u64,
+    pubkey: Binary,
+    signature: Binary,
 ) -> Result<Response, ContractError> {
-    // Allow users to delete their own data or use a specific key
-    let storage_key = if key.is_empty() {
-        info.sender.to_string()
-    } else {
-        key
+    ensure_not_paused(deps.as_ref())?;
+
+    let payload = SignedWrite {
+        chain_id: env.block.chain_id.clone(),
+        contract: env.contract.address.to_string(),
+        key: key.clone(),
+        value: value.clone(),
+        nonce,
     };
+    let hash = Sha256::digest(to_json_vec(&payload)?);
+    if !deps.api.secp256k1_verify(&hash, &signature, &pubkey)? {
+        return Err(ContractError::InvalidSignature {});
+    }
+
+    let owner = pubkey_to_addr(deps.as_ref(), &pubkey)?;
+    let expected = NONCES.may_load(deps.storage, &owner)?.unwrap_or_default();
+    if nonce != expected {
+        return Err(ContractError::InvalidNonce { expected, got: nonce });
+    }
+    NONCES.save(deps.storage, &owner, &(expected + 1))?;
+
+    // The relayer pays gas and may attach the deposit; refunds still go to the owner
+    let old = load_value(deps.as_ref(), &env, &owner, &key)?;
+    let bytes_before = stored_bytes(deps.as_ref(), &owner)?;
+    let version = save_value(deps.branch(), &env, &owner, &owner, &key, &value)?;
+    set_expiry(deps.branch(), &env, &owner, &key, None)?;
+    let hooks = hook_messages(deps.as_ref(), &owner, &key, old.as_deref(), Some(&value))?;
+    let refund = settle_deposit(deps, &owner, &info.funds, Some(bytes_before))?;
+
+    Ok(Response::new()
+        .add_messages(refund)
+        .add_submessages(hooks)
+        .add_attribute("method", "set_value_signed")
+        .add_attribute("owner", owner.to_string())
+        .add_attribute("key", key)
+        .add_attribute("version", version.to_string())
+        .add_attribute("nonce", nonce.to_string())
+        .add_attribute("relayer", info.sender.to_string()))
+}
+
+fn execute_set_profile(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    profile: PlayerProfile,
+    expected_version: Option<u64>,
+) -> Result<Response, ContractError> {
+    ensure_not_paused(deps.as_ref())?;
+    ensure_version(deps.as_ref(), &info.sender, "", expected_version)?;
+    validate_profile(&profile)?;
+
+    let value = to_json_string(&profile)?;
+    let old = load_value(deps.as_ref(), &env, &info.sender, "")?;
+    let bytes_before = stored_bytes(deps.as_ref(), &info.sender)?;
+    let version = save_value(deps.branch(), &env, &info.sender, &info.sender, "", &value)?;
+    set_expiry(deps.branch(), &env, &info.sender, "", None)?;
+    let hooks = hook_messages(deps.as_ref(), &info.sender, "", old.as_deref(), Some(&value))?;
+    let refund = settle_deposit(deps, &info.sender, &info.funds, Some(bytes_before))?;
+
+    Ok(Response::new()
+        .add_messages(refund)
+        .add_submessages(hooks)
+        .add_attribute("method", "set_profile")
+        .add_attribute("owner", info.sender.to_string())
+        .add_attribute("version", version.to_string())
+        .add_attribute("pets", profile.pets.len().to_string()))
+}
+
+fn execute_delete_value(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    owner: Option<String>,
+    key: String,
+    expected_version: Option<u64>,
+) -> Result<Response, ContractError> {
+    ensure_not_paused(deps.as_ref())?;
+    let owner = resolve_owner(deps.as_ref(), &env, &info, owner, &key)?;
+    ensure_version(deps.as_ref(), &owner, &key, expected_version)?;
 
-    USER_DATA.remove(deps.storage, &storage_key);
+    let old = load_value(deps.as_ref(), &env, &owner, &key)?;
+    remove_value(deps.branch(), &env, &info.sender, &owner, &key)?;
+    let hooks = hook_messages(deps.as_ref(), &owner, &key, old.as_deref(), None)?;
+    let refund = settle_deposit(deps, &owner, &info.funds, None)?;
 
     Ok(Response::new()
+        .add_messages(refund)
+        .add_submessages(hooks)
         .add_attribute("method", "delete_value")
-        .add_attribute("key", storage_key)
+        .add_attribute("owner", owner.to_string())
+        .add_attribute("key", key)
         .add_attribute("sender", info.sender.to_string()))
 }
 
-#[entry_point]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::GetValue { key } => {
-            let value = USER_DATA.may_load(deps.storage, &key)?;
-            to_json_binary(&value)
-        }
-        QueryMsg::GetValueByUser { address } => {
-            // Validate the address first
-            let addr = deps.api.addr_validate(&address)?;
-            let value = USER_DATA.may_load(deps.storage, addr.as_str())?;
-            match value {
-                Some(v) => to_json_binary(&v),
-                None => Err(StdError::generic_err("No value found for user")),
-            }
-        }
-        QueryMsg::GetAllValues {} => {
-            let values: StdResult<Vec<_>> = USER_DATA
-                .range(deps.storage, None, None, cosmwasm_std::Order::Ascending)
-                .collect();
-            to_json_binary(&values?)
-        }
+fn execute_grant_write(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    grantee: String,
+    keys: Vec<String>,
+    prefixes: Vec<String>,
+    expires_at: Option<u64>,
+) -> Result<Response, ContractError> {
+    let grantee = deps.api.addr_validate(&grantee)?;
+    if grantee == info.sender {
+        return Err(ContractError::InvalidGrant { reason: "cannot grant to yourself".to_string() });
+    }
+    if keys.is_empty() && prefixes.is_empty() {
+        return Err(ContractError::InvalidGrant { reason: "no keys or prefixes".to_string() });
+    }
+    if expires_at.is_some_and(|expires_at| expires_at <= env.block.time.seconds()) {
+        return Err(ContractError::InvalidGrant { reason: "already expired".to_string() });
     }
+
+    let grant = Grant {
+        grantee: grantee.clone(),
+        keys,
+        prefixes,
+        expires_at,
+    };
+    GRANTS.save(deps.storage, (&info.sender, &grantee), &grant)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "grant_write")
+        .add_attribute("owner", info.sender.to_string())
+        .add_attribute("grantee", grantee.to_string()))
+}
+
+fn execute_revoke_write(
+    deps: DepsMut,
+    info: MessageInfo,
+    grantee: String,
+) -> Result<Response, ContractError> {
+    let grantee = deps.api.addr_validate(&grantee)?;
+    GRANTS.remove(deps.storage, (&info.sender, &grantee));
+
+    Ok(Response::new()
+        .add_attribute("method", "revoke_write")
+        .add_attribute("owner", info.sender.to_string())
+        .add_attribute("grantee", grantee.to_string()))
+}
+
+fn execute_claim_username(
+    deps: DepsMut,
+    info: MessageInfo,
+    username: String,
+) -> Result<Response, ContractError> {
+    ensure_not_paused(deps.as_ref())?;
+    let normalized = normalize_username(&username)?;
+
+    if USERNAMES.has(deps.storage, &normalized) {
+        return Err(ContractError::UsernameTaken { username });
+    }
+    if USERNAME_OF.has(deps.storage, &info.sender) {
+        return Err(ContractError::AlreadyHasUsername {});
+    }
+
+    let record = UsernameRecord {
+        username: username.clone(),
+        owner: info.sender.clone(),
+    };
+    USERNAMES.save(deps.storage, &normalized, &record)?;
+    USERNAME_OF.save(deps.storage, &info.sender, &normalized)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "claim_username")
+        .add_attribute("username", username)
+        .add_attribute("owner", info.sender.to_string()))
+}
+
+fn execute_release_username(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    ensure_not_paused(deps.as_ref())?;
+    let normalized = USERNAME_OF
+        .may_load(deps.storage, &info.sender)?
+        .ok_or_else(|| StdError::not_found("username"))?;
+
+    USERNAMES.remove(deps.storage, &normalized);
+    USERNAME_OF.remove(deps.storage, &info.sender);
+
+    Ok(Response::new()
+        .add_attribute("method", "release_username")
+        .add_attribute("username", normalized)
+        .add_attribute("owner", info.sender.to_string()))
+}
+
+fn execute_transfer_username(
+    deps: DepsMut,
+    info: MessageInfo,
+    recipient: String,
+) -> Result<Response, ContractError> {
+    ensure_not_paused(deps.as_ref())?;
+    let recipient = deps.api.addr_validate(&recipient)?;
+    let normalized = USERNAME_OF
+        .may_load(deps.storage, &info.sender)?
+        .ok_or_else(|| StdError::not_found("username"))?;
+
+    if USERNAME_OF.has(deps.storage, &recipient) {
+        return Err(ContractError::AlreadyHasUsername {});
+    }
+
+    let mut record = USERNAMES.load(deps.storage, &normalized)?;
+    record.owner = recipient.clone();
+    USERNAMES.save(deps.storage, &normalized, &record)?;
+    USERNAME_OF.remove(deps.storage, &info.sender);
+    USERNAME_OF.save(deps.storage, &recipient, &normalized)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "transfer_username")
+        .add_attribute("username", record.username)
+        .add_attribute("owner", info.sender.to_string())
+        .add_attribute("recipient", recipient.to_string()))
+}
+
+fn execute_send_friend_request(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    to: String,
+) -> Result<Response, ContractError> {
+    ensure_not_paused(deps.as_ref())?;
+    let to = deps.api.addr_validate(&to)?;
+
+    if to == info.sender {
+        return Err(ContractError::CannotFriendSelf {});
+    }
+    if BLOCKS.has(deps.storage, (&to, &info.sender))
+        || BLOCKS.has(deps.storage, (&info.sender, &to))
+    {
+        return Err(ContractError::Blocked {});
+    }
+    if FRIENDS.has(deps.storage, (&info.sender, &to)) {
+        return Err(ContractError::AlreadyFriends {});
+    }
+
+    if FRIEND_REQUESTS.has(deps.storage, (&info.sender, &to)) {
+        return execute_accept_friend_request(deps, env, info, to.to_string());
+    }
+
+    FRIEND_REQUESTS.save(deps.storage, (&to, &info.sender), &env.block.time.seconds())?;
+
+    Ok(Response::new()
+        .add_attribute("method", "send_friend_request")
+        .add_attribute("from", info.sender.to_string())
+        .add_attribute("to", to.to_string()))
+}
+
+fn execute_accept_friend_request(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    from: String,
+) -> Result<Response, ContractError> {
+    ensure_not_paused(deps.as_ref())?;
+    let from = deps.api.addr_validate(&from)?;
+
+    if !FRIEND_REQUESTS.has(deps.storage, (&info.sender, &from)) {
+        return Err(ContractError::FriendRequestNotFound {});
+    }
+
+    FRIEND_REQUESTS.remove(deps.storage, (&info.sender, &from));
+    FRIEND_REQUESTS.remove(deps.storage, (&from, &info.sender));
+    let since = env.block.time.seconds();
+    FRIENDS.save(deps.storage, (&info.sender, &from), &since)?;
+    FRIENDS.save(deps.storage, (&from, &info.sender), &since)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "accept_friend_request")
+        .add_attribute("from", from.to_string())
+        .add_attribute("to", info.sender.to_string()))
+}
+
+fn execute_reject_friend_request(
+    deps: DepsMut,
+    info: MessageInfo,
+    from: String,
+) -> Result<Response, ContractError> {
+    ensure_not_paused(deps.as_ref())?;
+    let from = deps.api.addr_validate(&from)?;
+
+    if !FRIEND_REQUESTS.has(deps.storage, (&info.sender, &from)) {
+        return Err(ContractError::FriendRequestNotFound {});
+    }
+    FRIEND_REQUESTS.remove(deps.storage, (&info.sender, &from));
+
+    Ok(Response::new()
+        .add_attribute("method", "reject_friend_request")
+        .add_attribute("from", from.to_string())
+        .add_attribute("to", info.sender.to_string()))
+}
+
+fn execute_remove_friend(
+    deps: DepsMut,
+    info: MessageInfo,
+    friend: String,
+) -> Result<Response, ContractError> {
+    ensure_not_paused(deps.as_ref())?;
+    let friend = deps.api.addr_validate(&friend)?;
+
+    if !FRIENDS.has(deps.storage, (&info.sender, &friend)) {
+        return Err(ContractError::NotFriends {});
+    }
+    FRIENDS.remove(deps.storage, (&info.sender, &friend));
+    FRIENDS.remove(deps.storage, (&friend, &info.sender));
+
+    Ok(Response::new()
+        .add_attribute("method", "remove_friend")
+        .add_attribute("owner", info.sender.to_string())
+        .add_attribute("friend", friend.to_string()))
+}
+
+fn execute_block_user(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    address: String,
+) -> Result<Response, ContractError> {
+    ensure_not_paused(deps.as_ref())?;
+    let address = deps.api.addr_validate(&address)?;
+
+    if address == info.sender {
+        return Err(ContractError::CannotFriendSelf {});
+    }
+
+    BLOCKS.save(deps.storage, (&info.sender, &address), &env.block.time.seconds())?;
+    FRIENDS.remove(deps.storage, (&info.sender, &address));
+    FRIENDS.remove(deps.storage, (&address, &info.sender));
+    FRIEND_REQUESTS.remove(deps.storage, (&info.sender, &address));
+    FRIEND_REQUESTS.remove(deps.storage, (&address, &info.sender));
+
+    Ok(Response::new()
+        .add_attribute("method", "block_user")
+        .add_attribute("owner", info.sender.to_string())
+        .add_attribute("blocked", address.to_string()))
+}
+
+fn execute_unblock_user(
+    deps: DepsMut,
+    info: MessageInfo,
+    address: String,
+) -> Result<Response, ContractError> {
+    ensure_not_paused(deps.as_ref())?;
+    let address = deps.api.addr_validate(&address)?;
+    BLOCKS.remove(deps.storage, (&info.sender, &address));
+
+    Ok(Response::new()
+        .add_attribute("method", "unblock_user")
+        .add_attribute("owner", info.sender.to_string())
+        .add_attribute("unblocked", address.to_string()))
+}
+
+fn execute_patch_value(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    key: String,
+    patch: String,
+    expected_version: Option<u64>,
+) -> Result<Response, ContractError> {
+    ensure_not_paused(deps.as_ref())?;
+    ensure_version(deps.as_ref(), &info.sender, &key, expected_version)?;
+
+    let current = load_value(deps.as_ref(), &env, &info.sender, &key)?
+        .ok_or_else(|| StdError::not_found("user value"))?;
+    let mut target: serde_json::Value = serde_json::from_str(&current)
+        .map_err(|_| ContractError::NotAnObject {})?;
+    let patch: serde_json::Value = serde_json::from_str(&patch)
+        .map_err(|err| ContractError::InvalidJson { reason: err.to_string() })?;
+    // A non-object patch would replace the whole document, so only objects are accepted
+    if !target.is_object() || !patch.is_object() {
+        return Err(ContractError::NotAnObject {});
+    }
+
+    merge_patch(&mut target, &patch);
+    let value = serde_json::to_string(&target)
+        .map_err(|err| ContractError::InvalidJson { reason: err.to_string() })?;
+    let bytes_before = stored_bytes(deps.as_ref(), &info.sender)?;
+    let version = save_value(deps.branch(), &env, &info.sender, &info.sender, &key, &value)?;
+    let hooks = hook_messages(deps.as_ref(), &info.sender, &key, Some(&current), Some(&value))?;
+    let refund = settle_deposit(deps, &info.sender, &info.funds, Some(bytes_before))?;
+
+    Ok(Response::new()
+        .add_messages(refund)
+        .add_submessages(hooks)
+        .add_attribute("method", "patch_value")
+        .add_attribute("owner", info.sender.to_string())
+        .add_attribute("key", key)
+        .add_attribute("version", version.to_string()))
+}
+
+fn execute_batch(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    ops: Vec<SetOrDelete>,
+) -> Result<Response, ContractError> {
+    ensure_not_paused(deps.as_ref())?;
+    if ops.len() > MAX_BATCH_OPS {
+        return Err(ContractError::BatchTooLarge { max: MAX_BATCH_OPS as u32 });
+    }
+
+    let mut response = Response::new()
+        .add_attribute("method", "batch")
+        .add_attribute("owner", info.sender.to_string())
+        .add_attribute("ops", ops.len().to_string());
+
+    let bytes_before = stored_bytes(deps.as_ref(), &info.sender)?;
+    // Any failing op returns an error, which reverts the earlier ops with the tx
+    for (index, op) in ops.into_iter().enumerate() {
+        let (action, key, version, old, new) = match op {
+            SetOrDelete::Set { key, value, expected_version, ttl } => {
+                ensure_version(deps.as_ref(), &info.sender, &key, expected_version)?;
+                let old = load_value(deps.as_ref(), &env, &info.sender, &key)?;
+                let version =
+                    save_value(deps.branch(), &env, &info.sender, &info.sender, &key, &value)?;
+                set_expiry(deps.branch(), &env, &info.sender, &key, ttl)?;
+                ("set", key, version, old, Some(value))
+            }
+            SetOrDelete::Delete { key, expected_version } => {
+                ensure_version(deps.as_ref(), &info.sender, &key, expected_version)?;
+                let old = load_value(deps.as_ref(), &env, &info.sender, &key)?;
+                let version = remove_value(deps.branch(), &env, &info.sender, &info.sender, &key)?;
+                ("delete", key, version, old, None)
+            }
+        };
+        response = response
+            .add_submessages(hook_messages(
+                deps.as_ref(), &info.sender, &key, old.as_deref(), new.as_deref(),
+            )?)
+            .add_attribute(format!("op_{}_action", index), action)
+            .add_attribute(format!("op_{}_key", index), key)
+            .add_attribute(format!("op_{}_version", index), version.to_string());
+    }
+
+    let refund = settle_deposit(deps, &info.sender, &info.funds, Some(bytes_before))?;
+    Ok(response.add_messages(refund))
+}
+
+fn execute_prune_expired(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    start_after: Option<ValueCursor>,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let cursor = start_after
+        .map(|cursor| deps.api.addr_validate(&cursor.owner).map(|addr| (addr, cursor.key)))
+        .transpose()?;
+    let start = cursor
+        .as_ref()
+        .map(|(addr, key)| Bound::exclusive((addr, key.as_str())));
+
+    let scanned: Vec<((Addr, String), Expiry)> = EXPIRIES
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<_>>()?;
+
+    let mut removed = 0u64;
+    let mut owners: Vec<&Addr> = vec![];
+    let mut hooks = vec![];
+    for ((owner, key), expiry) in &scanned {
+        if expiry.is_expired(&env) {
+            // Expired values read as absent, so the hash comes from the raw entry
+            let old = OWNER_DATA.may_load(deps.storage, (owner, key))?;
+            remove_value(deps.branch(), &env, &info.sender, owner, key)?;
+            hooks.extend(hook_messages(deps.as_ref(), owner, key, old.as_deref(), None)?);
+            removed += 1;
+            if !owners.contains(&owner) {
+                owners.push(owner);
+            }
+        }
+    }
+
+    // Freed deposits go back to the owners, not the caller
+    let mut refunds = vec![];
+    for owner in owners {
+        refunds.extend(settle_deposit(deps.branch(), owner, &[], None)?);
+    }
+
+    let pruned = PRUNED_COUNT.may_load(deps.storage)?.unwrap_or_default() + removed;
+    PRUNED_COUNT.save(deps.storage, &pruned)?;
+
+    let mut response = Response::new()
+        .add_messages(refunds)
+        .add_submessages(hooks)
+        .add_attribute("method", "prune_expired")
+        .add_attribute("scanned", scanned.len().to_string())
+        .add_attribute("removed", removed.to_string());
+    // Cursor for the next call; absent once the end of the index was reached
+    if scanned.len() == limit {
+        if let Some(((owner, key), _)) = scanned.last() {
+            response = response
+                .add_attribute("next_owner", owner.to_string())
+                .add_attribute("next_key", key);
+        }
+    }
+
+    Ok(response)
+}
+
+fn execute_revert_value(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    key: String,
+    version: u64,
+) -> Result<Response, ContractError> {
+    ensure_not_paused(deps.as_ref())?;
+
+    let previous = ENTRY_HISTORY
+        .may_load(deps.storage, (&info.sender, &key, version))?
+        .ok_or(ContractError::VersionNotFound { version })?;
+
+    let old = load_value(deps.as_ref(), &env, &info.sender, &key)?;
+    let bytes_before = stored_bytes(deps.as_ref(), &info.sender)?;
+    let new_version = match &previous.value {
+        Some(value) => {
+            let new_version =
+                save_value(deps.branch(), &env, &info.sender, &info.sender, &key, value)?;
+            set_expiry(deps.branch(), &env, &info.sender, &key, None)?;
+            new_version
+        }
+        None => remove_value(deps.branch(), &env, &info.sender, &info.sender, &key)?,
+    };
+    let hooks = hook_messages(
+        deps.as_ref(), &info.sender, &key, old.as_deref(), previous.value.as_deref(),
+    )?;
+    let refund = settle_deposit(deps, &info.sender, &info.funds, Some(bytes_before))?;
+
+    Ok(Response::new()
+        .add_messages(refund)
+        .add_submessages(hooks)
+        .add_attribute("method", "revert_value")
+        .add_attribute("owner", info.sender.to_string())
+        .add_attribute("key", key)
+        .add_attribute("reverted_to", version.to_string())
+        .add_attribute("version", new_version.to_string()))
+}
+
+fn execute_admin_set_value(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    owner: String,
+    key: String,
+    value: String,
+) -> Result<Response, ContractError> {
+    ensure_admin(deps.as_ref(), &info)?;
+    let owner_addr = deps.api.addr_validate(&owner)?;
+
+    let old = load_value(deps.as_ref(), &env, &owner_addr, &key)?;
+    save_value(deps.branch(), &env, &info.sender, &owner_addr, &key, &value)?;
+    set_expiry(deps.branch(), &env, &owner_addr, &key, None)?;
+    let hooks = hook_messages(deps.as_ref(), &owner_addr, &key, old.as_deref(), Some(&value))?;
+    let refund = settle_deposit(deps.branch(), &owner_addr, &[], None)?;
+    let log_id = record_admin_action(deps, &env, &info, "set_value", &owner_addr, &key, None)?;
+
+    Ok(Response::new()
+        .add_messages(refund)
+        .add_submessages(hooks)
+        .add_attribute("method", "admin_set_value")
+        .add_attribute("admin_override", "true")
+        .add_attribute("admin_log_id", log_id.to_string())
+        .add_attribute("owner", owner_addr.to_string())
+        .add_attribute("key", key)
+        .add_attribute("sender", info.sender.to_string()))
+}
+
+fn execute_admin_delete_value(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    owner: String,
+    key: String,
+) -> Result<Response, ContractError> {
+    ensure_admin(deps.as_ref(), &info)?;
+    let owner_addr = deps.api.addr_validate(&owner)?;
+
+    let old = load_value(deps.as_ref(), &env, &owner_addr, &key)?;
+    remove_value(deps.branch(), &env, &info.sender, &owner_addr, &key)?;
+    let hooks = hook_messages(deps.as_ref(), &owner_addr, &key, old.as_deref(), None)?;
+    let refund = settle_deposit(deps.branch(), &owner_addr, &[], None)?;
+    let log_id = record_admin_action(deps, &env, &info, "delete_value", &owner_addr, &key, None)?;
+
+    Ok(Response::new()
+        .add_messages(refund)
+        .add_submessages(hooks)
+        .add_attribute("method", "admin_delete_value")
+        .add_attribute("admin_override", "true")
+        .add_attribute("admin_log_id", log_id.to_string())
+        .add_attribute("owner", owner_addr.to_string())
+        .add_attribute("key", key)
+        .add_attribute("sender", info.sender.to_string()))
+}
+
+fn execute_migrate_legacy_value(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    legacy_key: String,
+    owner: String,
+    key: String,
+) -> Result<Response, ContractError> {
+    ensure_admin(deps.as_ref(), &info)?;
+    let owner_addr = deps.api.addr_validate(&owner)?;
+
+    let value = USER_DATA
+        .may_load(deps.storage, &legacy_key)?
+        .ok_or_else(|| StdError::not_found("legacy value"))?;
+
+    let old = load_value(deps.as_ref(), &env, &owner_addr, &key)?;
+    save_value(deps.branch(), &env, &info.sender, &owner_addr, &key, &value)?;
+    set_expiry(deps.branch(), &env, &owner_addr, &key, None)?;
+    USER_DATA.remove(deps.storage, &legacy_key);
+    let hooks = hook_messages(deps.as_ref(), &owner_addr, &key, old.as_deref(), Some(&value))?;
+    let log_id = record_admin_action(
+        deps, &env, &info, "migrate_legacy_value", &owner_addr, &key, None,
+    )?;
+
+    Ok(Response::new()
+        .add_submessages(hooks)
+        .add_attribute("method", "migrate_legacy_value")
+        .add_attribute("admin_override", "true")
+        .add_attribute("admin_log_id", log_id.to_string())
+        .add_attribute("legacy_key", legacy_key)
+        .add_attribute("owner", owner_addr.to_string())
+        .add_attribute("key", key))
+}
+
+fn execute_import_entries(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    entries: Vec<ImportEntry>,
+    skip_existing: bool,
+) -> Result<Response, ContractError> {
+    ensure_admin(deps.as_ref(), &info)?;
+    if entries.len() > MAX_IMPORT_ENTRIES {
+        return Err(ContractError::BatchTooLarge { max: MAX_IMPORT_ENTRIES as u32 });
+    }
+
+    let mut imported = 0u32;
+    let mut skipped = 0u32;
+    let mut hooks = vec![];
+    for entry in entries {
+        let owner = deps.api.addr_validate(&entry.owner)?;
+        let expired = entry.expiry.as_ref().is_some_and(|expiry| expiry.is_expired(&env));
+        let old = load_value(deps.as_ref(), &env, &owner, &entry.key)?;
+        if expired || (skip_existing && old.is_some()) {
+            skipped += 1;
+            continue;
+        }
+
+        let version =
+            save_value(deps.branch(), &env, &info.sender, &owner, &entry.key, &entry.value)?;
+        // Versions never go backwards, so the exported number only applies if it is ahead.
+        // Only the current value is exported, so older history is not restored.
+        if let Some(exported) = entry.version.filter(|exported| exported.version >= version) {
+            let history: Vec<u64> = ENTRY_HISTORY
+                .prefix((&owner, &entry.key))
+                .keys(deps.storage, None, None, Order::Ascending)
+                .collect::<StdResult<_>>()?;
+            for old in history {
+                ENTRY_HISTORY.remove(deps.storage, (&owner, &entry.key, old));
+            }
+            ENTRY_HISTORY.save(
+                deps.storage,
+                (&owner, &entry.key, exported.version),
+                &ValueVersion {
+                    version: exported.version,
+                    value: Some(entry.value.clone()),
+                    block_height: exported.block_height,
+                    writer: exported.writer.clone(),
+                },
+            )?;
+            ENTRY_VERSIONS.save(deps.storage, (&owner, &entry.key), &exported)?;
+        }
+        match &entry.expiry {
+            Some(expiry) => EXPIRIES.save(deps.storage, (&owner, &entry.key), expiry)?,
+            None => EXPIRIES.remove(deps.storage, (&owner, &entry.key)),
+        }
+        hooks.extend(hook_messages(
+            deps.as_ref(), &owner, &entry.key, old.as_deref(), Some(&entry.value),
+        )?);
+        imported += 1;
+    }
+
+    Ok(Response::new()
+        .add_submessages(hooks)
+        .add_attribute("method", "import_entries")
+        .add_attribute("admin", info.sender.to_string())
+        .add_attribute("imported", imported.to_string())
+        .add_attribute("skipped", skipped.to_string()))
+}
+
+fn execute_takedown_value(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    owner: String,
+    key: String,
+    reason: String,
+) -> Result<Response, ContractError> {
+    ensure_admin(deps.as_ref(), &info)?;
+    let owner_addr = deps.api.addr_validate(&owner)?;
+
+    let old = load_value(deps.as_ref(), &env, &owner_addr, &key)?
+        .ok_or_else(|| StdError::not_found("user value"))?;
+
+    remove_value(deps.branch(), &env, &info.sender, &owner_addr, &key)?;
+    let hooks = hook_messages(deps.as_ref(), &owner_addr, &key, Some(&old), None)?;
+    let refund = settle_deposit(deps.branch(), &owner_addr, &[], None)?;
+    let log_id = record_admin_action(
+        deps, &env, &info, "takedown", &owner_addr, &key, Some(reason.clone()),
+    )?;
+
+    Ok(Response::new()
+        .add_messages(refund)
+        .add_submessages(hooks)
+        .add_attribute("method", "takedown_value")
+        .add_attribute("admin_override", "true")
+        .add_attribute("admin_log_id", log_id.to_string())
+        .add_attribute("owner", owner_addr.to_string())
+        .add_attribute("key", key)
+        .add_attribute("reason", reason))
+}
+
+fn execute_update_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    paused: Option<bool>,
+    limits: Option<Limits>,
+    deposit: Option<DepositConfig>,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+
+    // Only admin can update config
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if let Some(paused) = paused {
+        config.paused = paused;
+    }
+
+    if let Some(limits) = limits {
+        config.limits = limits;
+    }
+
+    if let Some(deposit) = deposit {
+        let locked = TOTAL_DEPOSITS.may_load(deps.storage)?.unwrap_or_default();
+        if deposit.denom != config.deposit.denom && !locked.is_zero() {
+            return Err(ContractError::DepositsLocked { denom: config.deposit.denom });
+        }
+        config.deposit = deposit;
+    }
+
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "update_config")
+        .add_attribute("paused", config.paused.to_string()))
+}
+
+fn execute_add_hook(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    address: String,
+) -> Result<Response, ContractError> {
+    ensure_admin(deps.as_ref(), &info)?;
+    let hook = deps.api.addr_validate(&address)?;
+
+    if !HOOKS.has(deps.storage, &hook) {
+        let count = HOOKS.keys(deps.storage, None, None, Order::Ascending).count();
+        if count >= MAX_HOOKS {
+            return Err(ContractError::TooManyHooks { max: MAX_HOOKS as u32 });
+        }
+        HOOKS.save(deps.storage, &hook, &env.block.height)?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("method", "add_hook")
+        .add_attribute("hook", hook.to_string()))
+}
+
+fn execute_remove_hook(
+    deps: DepsMut,
+    info: MessageInfo,
+    address: String,
+) -> Result<Response, ContractError> {
+    ensure_admin(deps.as_ref(), &info)?;
+    let hook = deps.api.addr_validate(&address)?;
+    HOOKS.remove(deps.storage, &hook);
+
+    Ok(Response::new()
+        .add_attribute("method", "remove_hook")
+        .add_attribute("hook", hook.to_string()))
+}
+
+fn execute_propose_admin(
+    deps: DepsMut,
+    info: MessageInfo,
+    admin: String,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let pending_admin = deps.api.addr_validate(&admin)?;
+    config.pending_admin = Some(pending_admin.clone());
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "propose_admin")
+        .add_attribute("admin", config.admin.to_string())
+        .add_attribute("pending_admin", pending_admin.to_string()))
+}
+
+fn execute_accept_admin(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+
+    if config.pending_admin.as_ref() != Some(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let previous_admin = std::mem::replace(&mut config.admin, info.sender.clone());
+    config.pending_admin = None;
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "accept_admin")
+        .add_attribute("previous_admin", previous_admin.to_string())
+        .add_attribute("admin", config.admin.to_string()))
+}
+
+// Hook calls reply only on error; swallowing the error keeps the owner's write
+#[entry_point]
+pub fn reply(_deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match (msg.id, msg.result) {
+        (HOOK_REPLY_ID, SubMsgResult::Err(err)) => Ok(Response::new()
+            .add_attribute("method", "hook_failed")
+            .add_attribute("error", err)),
+        (id, _) => Err(StdError::generic_err(format!("Unknown reply id: {}", id)).into()),
+    }
+}
+
+#[entry_point]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Config {} => to_json_binary(&CONFIG.load(deps.storage)?),
+        QueryMsg::GetValue { key, owner } => {
+            let response = match owner {
+                Some(owner) => {
+                    let addr = deps.api.addr_validate(&owner)?;
+                    ValueResponse {
+                        value: load_value(deps, &env, &addr, &key)?,
+                        version: current_version(deps, &addr, &key)?,
+                    }
+                }
+                None => ValueResponse {
+                    value: USER_DATA.may_load(deps.storage, &key)?,
+                    version: 0,
+                },
+            };
+            to_json_binary(&response)
+        }
+        QueryMsg::GetValueByUser { address } => {
+            // Validate the address first
+            let addr = deps.api.addr_validate(&address)?;
+            let value = load_value(deps, &env, &addr, "")?;
+            match value {
+                Some(v) => to_json_binary(&v),
+                None => Err(StdError::generic_err("No value found for user")),
+            }
+        }
+        QueryMsg::ResolveUsername { username } => {
+            let normalized = username.to_ascii_lowercase();
+            to_json_binary(&USERNAMES.may_load(deps.storage, &normalized)?)
+        }
+        QueryMsg::UsernameOf { address } => {
+            let addr = deps.api.addr_validate(&address)?;
+            let record = match USERNAME_OF.may_load(deps.storage, &addr)? {
+                Some(normalized) => USERNAMES.may_load(deps.storage, &normalized)?,
+                None => None,
+            };
+            to_json_binary(&record)
+        }
+        QueryMsg::Friends { address, start_after, limit } => {
+            let addr = deps.api.addr_validate(&address)?;
+            let start_addr = start_after.map(|a| deps.api.addr_validate(&a)).transpose()?;
+            let start = start_addr.as_ref().map(Bound::exclusive);
+            let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+            let friends: StdResult<Vec<Addr>> = FRIENDS
+                .prefix(&addr)
+                .keys(deps.storage, start, None, Order::Ascending)
+                .take(limit)
+                .collect();
+            to_json_binary(&friends?)
+        }
+        QueryMsg::PendingFriendRequests { address, start_after, limit } => {
+            let addr = deps.api.addr_validate(&address)?;
+            let start_addr = start_after.map(|a| deps.api.addr_validate(&a)).transpose()?;
+            let start = start_addr.as_ref().map(Bound::exclusive);
+            let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+            let requests: StdResult<Vec<_>> = FRIEND_REQUESTS
+                .prefix(&addr)
+                .range(deps.storage, start, None, Order::Ascending)
+                .take(limit)
+                .map(|item| item.map(|(from, sent_at)| FriendRequest { from, sent_at }))
+                .collect();
+            to_json_binary(&requests?)
+        }
+        QueryMsg::MutualFriends { address, other, start_after, limit } => {
+            let addr = deps.api.addr_validate(&address)?;
+            let other = deps.api.addr_validate(&other)?;
+            let start_addr = start_after.map(|a| deps.api.addr_validate(&a)).transpose()?;
+            let start = start_addr.as_ref().map(Bound::exclusive);
+            let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+            let mutual: StdResult<Vec<Addr>> = FRIENDS
+                .prefix(&addr)
+                .keys(deps.storage, start, None, Order::Ascending)
+                .filter(|item| {
+                    item.as_ref().map_or(true, |friend| FRIENDS.has(deps.storage, (&other, friend)))
+                })
+                .take(limit)
+                .collect();
+            to_json_binary(&mutual?)
+        }
+        QueryMsg::GetProfile { address } => {
+            let addr = deps.api.addr_validate(&address)?;
+            let value = load_value(deps, &env, &addr, "")?
+                .ok_or_else(|| StdError::generic_err("No value found for user"))?;
+            // Blobs written through SetValue by old clients may not match the schema
+            let profile: PlayerProfile = from_json(value.as_bytes())
+                .map_err(|_| StdError::generic_err("Stored value is not a valid profile"))?;
+            to_json_binary(&profile)
+        }
+        QueryMsg::Deposit { address } => {
+            let addr = deps.api.addr_validate(&address)?;
+            let deposit = load_deposit_config(deps)?;
+            let usage = USAGE.may_load(deps.storage, &addr)?.unwrap_or_default();
+            to_json_binary(&DepositResponse {
+                locked: DEPOSITS.may_load(deps.storage, &addr)?.unwrap_or_default(),
+                required: deposit.price_per_byte.checked_mul(Uint128::from(usage.bytes))?,
+                denom: deposit.denom,
+                address: addr,
+            })
+        }
+        QueryMsg::Nonce { address } => {
+            let addr = deps.api.addr_validate(&address)?;
+            to_json_binary(&NonceResponse {
+                nonce: NONCES.may_load(deps.storage, &addr)?.unwrap_or_default(),
+                address: addr,
+            })
+        }
+        QueryMsg::Usage { address } => {
+            let addr = deps.api.addr_validate(&address)?;
+            let usage = USAGE.may_load(deps.storage, &addr)?.unwrap_or_default();
+            let limits = load_limits(deps)?;
+            to_json_binary(&UsageResponse {
+                address: addr,
+                keys: usage.keys,
+                bytes: usage.bytes,
+                max_keys: limits.max_keys_per_owner,
+                max_value_size: limits.max_value_size,
+            })
+        }
+        QueryMsg::PrunedCount {} => to_json_binary(&PrunedCountResponse {
+            pruned: PRUNED_COUNT.may_load(deps.storage)?.unwrap_or_default(),
+        }),
+        QueryMsg::Grants { owner, start_after, limit } => {
+            let addr = deps.api.addr_validate(&owner)?;
+            let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+            let start_addr = start_after.map(|a| deps.api.addr_validate(&a)).transpose()?;
+            let start = start_addr.as_ref().map(Bound::exclusive);
+            let now = env.block.time.seconds();
+            let grants: StdResult<Vec<_>> = GRANTS
+                .prefix(&addr)
+                .range(deps.storage, start, None, Order::Ascending)
+                .filter(|item| {
+                    item.as_ref().map_or(true, |(_, grant)| {
+                        grant.expires_at.filter(|expires_at| now >= *expires_at).is_none()
+                    })
+                })
+                .take(limit)
+                .map(|item| item.map(|(_, grant)| grant))
+                .collect();
+            to_json_binary(&grants?)
+        }
+        QueryMsg::ValueHistory { owner, key, limit } => {
+            let addr = deps.api.addr_validate(&owner)?;
+            let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+            let versions: StdResult<Vec<_>> = ENTRY_HISTORY
+                .prefix((&addr, &key))
+                .range(deps.storage, None, None, Order::Descending)
+                .take(limit)
+                .map(|item| item.map(|(_, version)| version))
+                .collect();
+            to_json_binary(&versions?)
+        }
+        QueryMsg::GetAllValues { owner, key_prefix, start_after, limit } => {
+            to_json_binary(&query_all_values(deps, &env, owner, key_prefix, start_after, limit)?)
+        }
+        QueryMsg::ExportEntries { start_after, limit } => {
+            to_json_binary(&query_export_entries(deps, &env, start_after, limit)?)
+        }
+        QueryMsg::AdminLog { start_after, limit } => {
+            let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+            let start = start_after.map(Bound::exclusive);
+            let actions: StdResult<Vec<_>> = ADMIN_LOG
+                .range(deps.storage, start, None, Order::Ascending)
+                .take(limit)
+                .map(|item| item.map(|(_, action)| action))
+                .collect();
+            to_json_binary(&actions?)
+        }
+        QueryMsg::Hooks {} => {
+            let hooks: StdResult<Vec<_>> = HOOKS
+                .keys(deps.storage, None, None, Order::Ascending)
+                .collect();
+            to_json_binary(&HooksResponse { hooks: hooks? })
+        }
+    }
+}
+
+fn query_all_values(
+    deps: Deps,
+    env: &Env,
+    owner: Option<String>,
+    key_prefix: Option<String>,
+    start_after: Option<ValueCursor>,
+    limit: Option<u32>,
+) -> StdResult<AllValuesResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let key_prefix = key_prefix.unwrap_or_default();
+
+    let values: Vec<ValueEntry> = match owner {
+        Some(owner) => {
+            let addr = deps.api.addr_validate(&owner)?;
+            if let Some(cursor) = &start_after {
+                if deps.api.addr_validate(&cursor.owner)? != addr {
+                    return Err(StdError::generic_err("Cursor belongs to another owner"));
+                }
+            }
+            // Keys are sorted, so a prefix match is a contiguous range; a cursor that sorts
+            // before the prefix must not start the scan outside it
+            let start = match &start_after {
+                Some(cursor) if cursor.key.as_str() >= key_prefix.as_str() => {
+                    Bound::exclusive(cursor.key.as_str())
+                }
+                _ => Bound::inclusive(key_prefix.as_str()),
+            };
+            OWNER_DATA
+                .prefix(&addr)
+                .range(deps.storage, Some(start), None, Order::Ascending)
+                .take_while(|item| {
+                    item.as_ref().map_or(true, |(key, _)| key.starts_with(&key_prefix))
+                })
+                .filter(|item| {
+                    item.as_ref().map_or(true, |(key, _)| !is_expired(deps, env, &addr, key))
+                })
+                .take(limit)
+                .map(|item| {
+                    item.map(|(key, value)| ValueEntry { owner: addr.clone(), key, value })
+                })
+                .collect::<StdResult<_>>()?
+        }
+        None => {
+            let cursor = start_after
+                .map(|cursor| deps.api.addr_validate(&cursor.owner).map(|addr| (addr, cursor.key)))
+                .transpose()?;
+            let start = cursor
+                .as_ref()
+                .map(|(addr, key)| Bound::exclusive((addr, key.as_str())));
+            // A sparse prefix could otherwise walk every owner's keys, so the scan is
+            // bounded and a short page still returns a cursor to where it stopped
+            let mut values = vec![];
+            let mut scanned = 0;
+            let mut last_scanned = None;
+            let range = OWNER_DATA.range(deps.storage, start, None, Order::Ascending);
+            for item in range.take(MAX_SCAN) {
+                let ((owner, key), value) = item?;
+                scanned += 1;
+                if key.starts_with(&key_prefix) && !is_expired(deps, env, &owner, &key) {
+                    values.push(ValueEntry { owner: owner.clone(), key: key.clone(), value });
+                }
+                last_scanned = Some(ValueCursor { owner: owner.to_string(), key });
+                if values.len() == limit {
+                    break;
+                }
+            }
+            if values.len() < limit && scanned == MAX_SCAN {
+                return Ok(AllValuesResponse { values, next: last_scanned });
+            }
+            values
+        }
+    };
+
+    let next = if values.len() == limit {
+        values.last().map(|entry| ValueCursor {
+            owner: entry.owner.to_string(),
+            key: entry.key.clone(),
+        })
+    } else {
+        None
+    };
+
+    Ok(AllValuesResponse { values, next })
+}
+
+fn query_export_entries(
+    deps: Deps,
+    env: &Env,
+    start_after: Option<ValueCursor>,
+    limit: Option<u32>,
+) -> StdResult<ExportResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let cursor = start_after
+        .map(|cursor| deps.api.addr_validate(&cursor.owner).map(|addr| (addr, cursor.key)))
+        .transpose()?;
+    let start = cursor
+        .as_ref()
+        .map(|(addr, key)| Bound::exclusive((addr, key.as_str())));
+
+    let entries = OWNER_DATA
+        .range(deps.storage, start, None, Order::Ascending)
+        .filter(|item| {
+            item.as_ref().map_or(true, |((owner, key), _)| !is_expired(deps, env, owner, key))
+        })
+        .take(limit)
+        .map(|item| {
+            let ((owner, key), value) = item?;
+            Ok(ExportEntry {
+                expiry: EXPIRIES.may_load(deps.storage, (&owner, &key))?,
+                version: ENTRY_VERSIONS.may_load(deps.storage, (&owner, &key))?,
+                owner,
+                key,
+                value,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let next = if entries.len() == limit {
+        entries.last().map(|entry| ValueCursor {
+            owner: entry.owner.to_string(),
+            key: entry.key.clone(),
+        })
+    } else {
+        None
+    };
+
+    Ok(ExportResponse { entries, next })
+}
+
+// Helper functions
+// 3-20 ASCII letters, digits or underscores, starting with a letter
+fn normalize_username(username: &str) -> Result<String, ContractError> {
+    let invalid = |reason: &str| ContractError::InvalidUsername { reason: reason.to_string() };
+
+    if username.len() < MIN_USERNAME_LENGTH || username.len() > MAX_USERNAME_LENGTH {
+        return Err(invalid("must be 3 to 20 characters"));
+    }
+    if !username.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return Err(invalid("must start with a letter"));
+    }
+    if !username.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(invalid("only letters, digits and underscores are allowed"));
+    }
+
+    Ok(username.to_ascii_lowercase())
+}
+
+fn ensure_admin(deps: Deps, info: &MessageInfo) -> Result<(), ContractError> {
+    // Deployments instantiated before the admin was stored have no config
+    match CONFIG.may_load(deps.storage)? {
+        Some(config) if config.admin == info.sender => Ok(()),
+        _ => Err(ContractError::Unauthorized {}),
+    }
+}
+
+// Cosmos account address: bech32(prefix, ripemd160(sha256(compressed pubkey)))
+fn pubkey_to_addr(deps: Deps, pubkey: &[u8]) -> Result<Addr, ContractError> {
+    if pubkey.len() != 33 {
+        return Err(ContractError::InvalidSignature {});
+    }
+    let hash = Ripemd160::digest(Sha256::digest(pubkey));
+    Ok(deps.api.addr_humanize(&CanonicalAddr::from(hash.to_vec()))?)
+}
+
+fn value_hash(value: Option<&str>) -> Option<Binary> {
+    value.map(|value| Binary::from(Sha256::digest(value.as_bytes()).to_vec()))
+}
+
+fn hook_messages(
+    deps: Deps,
+    owner: &Addr,
+    key: &str,
+    old: Option<&str>,
+    new: Option<&str>,
+) -> StdResult<Vec<SubMsg>> {
+    let msg = to_json_binary(&HookMsg::UserMapChanged {
+        owner: owner.to_string(),
+        key: key.to_string(),
+        old_hash: value_hash(old),
+        new_hash: value_hash(new),
+    })?;
+
+    HOOKS
+        .keys(deps.storage, None, None, Order::Ascending)
+        .map(|hook| {
+            let execute = WasmMsg::Execute {
+                contract_addr: hook?.to_string(),
+                msg: msg.clone(),
+                funds: vec![],
+            };
+            Ok(SubMsg::reply_on_error(execute, HOOK_REPLY_ID).with_gas_limit(HOOK_GAS_LIMIT))
+        })
+        .collect()
+}
+
+// Writes default to the sender's own namespace; other namespaces need a matching grant
+fn resolve_owner(
+    deps: Deps,
+    env: &Env,
+    info: &MessageInfo,
+    owner: Option<String>,
+    key: &str,
+) -> Result<Addr, ContractError> {
+    let owner = match owner {
+        Some(owner) => deps.api.addr_validate(&owner)?,
+        None => return Ok(info.sender.clone()),
+    };
+    if owner == info.sender {
+        return Ok(owner);
+    }
+
+    let grant = GRANTS.may_load(deps.storage, (&owner, &info.sender))?;
+    match grant {
+        Some(grant) if grant.allows(key, env.block.time.seconds()) => Ok(owner),
+        _ => Err(ContractError::Unauthorized {}),
+    }
+}
+
+fn ensure_not_paused(deps: Deps) -> Result<(), ContractError> {
+    match CONFIG.may_load(deps.storage)? {
+        Some(config) if config.paused => Err(ContractError::Paused {}),
+        _ => Ok(()),
+    }
+}
+
+fn current_version(deps: Deps, owner: &Addr, key: &str) -> StdResult<u64> {
+    Ok(ENTRY_VERSIONS
+        .may_load(deps.storage, (owner, key))?
+        .map(|entry| entry.version)
+        .unwrap_or_default())
+}
+
+fn ensure_version(
+    deps: Deps,
+    owner: &Addr,
+    key: &str,
+    expected: Option<u64>,
+) -> Result<(), ContractError> {
+    if let Some(expected) = expected {
+        let current = current_version(deps, owner, key)?;
+        if current != expected {
+            return Err(ContractError::VersionConflict { expected, current });
+        }
+    }
+    Ok(())
+}
+
+// RFC 7386: null removes a member, objects merge recursively, anything else replaces
+fn merge_patch(target: &mut serde_json::Value, patch: &serde_json::Value) {
+    let serde_json::Value::Object(patch_members) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = serde_json::Value::Object(serde_json::Map::new());
+    }
+    if let serde_json::Value::Object(target_members) = target {
+        for (name, value) in patch_members {
+            if value.is_null() {
+                target_members.remove(name);
+            } else {
+                merge_patch(
+                    target_members.entry(name.clone()).or_insert(serde_json::Value::Null),
+                    value,
+                );
+            }
+        }
+    }
+}
+
+fn validate_profile(profile: &PlayerProfile) -> Result<(), ContractError> {
+    let invalid = |reason: &str| ContractError::InvalidProfile { reason: reason.to_string() };
+
+    if profile.pets.len() > MAX_PROFILE_PETS {
+        return Err(invalid("too many pets"));
+    }
+    for entry in &profile.pets {
+        match entry {
+            ProfilePetEntry::Id(id) => {
+                if id.is_empty() || id.len() > MAX_PROFILE_FIELD_LENGTH {
+                    return Err(invalid("pet id is empty or too long"));
+                }
+            }
+            ProfilePetEntry::Pet(pet) => validate_pet(pet, "pet")?,
+        }
+    }
+
+    match &profile.starter_pet {
+        Some(pet) => validate_pet(pet, "starter pet")?,
+        None if profile.has_starter_pet => return Err(invalid("starter pet is missing")),
+        None => {}
+    }
+
+    Ok(())
+}
+
+fn validate_pet(pet: &ProfilePet, label: &str) -> Result<(), ContractError> {
+    let invalid = |reason: String| ContractError::InvalidProfile { reason };
+
+    if pet.id.is_empty() || pet.id.len() > MAX_PROFILE_FIELD_LENGTH {
+        return Err(invalid(format!("{label} id is empty or too long")));
+    }
+    if pet.name.is_empty() || pet.name.len() > MAX_PROFILE_FIELD_LENGTH {
+        return Err(invalid(format!("{label} name is empty or too long")));
+    }
+    if !PET_TYPES.contains(&pet.pet_type.as_str()) {
+        return Err(invalid("unknown pet type".to_string()));
+    }
+    if !PET_RARITIES.contains(&pet.rarity.as_str()) {
+        return Err(invalid("unknown pet rarity".to_string()));
+    }
+    Ok(())
+}
+
+fn load_limits(deps: Deps) -> StdResult<Limits> {
+    Ok(CONFIG
+        .may_load(deps.storage)?
+        .map(|config| config.limits)
+        .unwrap_or_default())
+}
+
+fn load_deposit_config(deps: Deps) -> StdResult<DepositConfig> {
+    Ok(CONFIG
+        .may_load(deps.storage)?
+        .map(|config| config.deposit)
+        .unwrap_or_default())
+}
+
+fn stored_bytes(deps: Deps, owner: &Addr) -> StdResult<u64> {
+    Ok(USAGE.may_load(deps.storage, owner)?.unwrap_or_default().bytes)
+}
+
+// Credits `funds` to the owner's locked deposit, then checks it against the bytes they store.
+// Anything above the requirement is refunded to the owner. The requirement is only enforced
+// when the write grew the owner's usage past `bytes_before`; deletes, admin and prune paths
+// pass `None` so they only ever refund.
+fn settle_deposit(
+    deps: DepsMut,
+    owner: &Addr,
+    funds: &[Coin],
+    bytes_before: Option<u64>,
+) -> Result<Option<BankMsg>, ContractError> {
+    let deposit = load_deposit_config(deps.as_ref())?;
+    let mut paid = Uint128::zero();
+    for coin in funds {
+        if coin.denom != deposit.denom {
+            return Err(ContractError::InvalidDeposit { denom: deposit.denom });
+        }
+        paid += coin.amount;
+    }
+
+    let usage = USAGE.may_load(deps.storage, owner)?.unwrap_or_default();
+    let required = deposit
+        .price_per_byte
+        .checked_mul(Uint128::from(usage.bytes))
+        .map_err(StdError::from)?;
+    let previous = DEPOSITS.may_load(deps.storage, owner)?.unwrap_or_default();
+    let locked = previous + paid;
+
+    let grew = bytes_before.is_some_and(|before| usage.bytes > before);
+    if grew && locked < required {
+        return Err(ContractError::InsufficientDeposit { required, locked });
+    }
+
+    let refund = locked.saturating_sub(required);
+    DEPOSITS.save(deps.storage, owner, &(locked - refund))?;
+    let total = TOTAL_DEPOSITS.may_load(deps.storage)?.unwrap_or_default();
+    TOTAL_DEPOSITS.save(deps.storage, &(total.saturating_sub(previous) + locked - refund))?;
+    if refund.is_zero() {
+        return Ok(None);
+    }
+
+    Ok(Some(BankMsg::Send {
+        to_address: owner.to_string(),
+        amount: vec![Coin {
+            denom: deposit.denom,
+            amount: refund,
+        }],
+    }))
+}
+
+// Writes the value as a new version and returns that version
+fn save_value(
+    deps: DepsMut,
+    env: &Env,
+    writer: &Addr,
+    owner: &Addr,
+    key: &str,
+    value: &str,
+) -> Result<u64, ContractError> {
+    let limits = load_limits(deps.as_ref())?;
+    if key.len() > limits.max_key_length as usize {
+        return Err(ContractError::KeyTooLong { max: limits.max_key_length });
+    }
+    if value.len() > limits.max_value_size as usize {
+        return Err(ContractError::QuotaExceeded {
+            reason: format!("value is larger than {} bytes", limits.max_value_size),
+        });
+    }
+
+    let mut usage = USAGE.may_load(deps.storage, owner)?.unwrap_or_default();
+    match OWNER_DATA.may_load(deps.storage, (owner, key))? {
+        Some(old) => usage.bytes = usage.bytes.saturating_sub(old.len() as u64),
+        None => {
+            if usage.keys >= limits.max_keys_per_owner {
+                return Err(ContractError::QuotaExceeded {
+                    reason: format!("owner already has {} keys", limits.max_keys_per_owner),
+                });
+            }
+            usage.keys += 1;
+        }
+    }
+    usage.bytes += value.len() as u64;
+    USAGE.save(deps.storage, owner, &usage)?;
+
+    OWNER_DATA.save(deps.storage, (owner, key), &value.to_string())?;
+    if key.is_empty() {
+        // The namespaced profile supersedes the legacy address-keyed entry
+        USER_DATA.remove(deps.storage, owner.as_str());
+    }
+
+    let version = record_version(deps, env, writer, owner, key, Some(value.to_string()), &limits)?;
+    Ok(version)
+}
+
+// Deletes are recorded as a version too, but the key's earlier values are dropped: deleted
+// keys don't count against quotas or deposits, and takedowns must not be revertible
+fn remove_value(
+    mut deps: DepsMut,
+    env: &Env,
+    writer: &Addr,
+    owner: &Addr,
+    key: &str,
+) -> StdResult<u64> {
+    let Some(old) = OWNER_DATA.may_load(deps.storage, (owner, key))? else {
+        if key.is_empty() {
+            USER_DATA.remove(deps.storage, owner.as_str());
+        }
+        // Nothing to delete, so no new version either
+        return current_version(deps.as_ref(), owner, key);
+    };
+
+    let mut usage = USAGE.may_load(deps.storage, owner)?.unwrap_or_default();
+    usage.keys = usage.keys.saturating_sub(1);
+    usage.bytes = usage.bytes.saturating_sub(old.len() as u64);
+    USAGE.save(deps.storage, owner, &usage)?;
+    OWNER_DATA.remove(deps.storage, (owner, key));
+    EXPIRIES.remove(deps.storage, (owner, key));
+    if key.is_empty() {
+        USER_DATA.remove(deps.storage, owner.as_str());
+    }
+
+    let limits = load_limits(deps.as_ref())?;
+    let version = record_version(deps.branch(), env, writer, owner, key, None, &limits)?;
+    trim_history(deps.storage, owner, key, version)?;
+    Ok(version)
+}
+
+// Removes every stored version of the key below `keep_from`
+fn trim_history(
+    storage: &mut dyn Storage,
+    owner: &Addr,
+    key: &str,
+    keep_from: u64,
+) -> StdResult<()> {
+    let stale: Vec<u64> = ENTRY_HISTORY
+        .prefix((owner, key))
+        .keys(storage, None, Some(Bound::exclusive(keep_from)), Order::Ascending)
+        .collect::<StdResult<_>>()?;
+    for version in stale {
+        ENTRY_HISTORY.remove(storage, (owner, key, version));
+    }
+    Ok(())
+}
+
+fn is_expired(deps: Deps, env: &Env, owner: &Addr, key: &str) -> bool {
+    EXPIRIES
+        .may_load(deps.storage, (owner, key))
+        .ok()
+        .flatten()
+        .is_some_and(|expiry| expiry.is_expired(env))
+}
+
+fn set_expiry(
+    deps: DepsMut,
+    env: &Env,
+    owner: &Addr,
+    key: &str,
+    ttl: Option<Ttl>,
+) -> Result<(), ContractError> {
+    let expiry = match ttl {
+        None => {
+            EXPIRIES.remove(deps.storage, (owner, key));
+            return Ok(());
+        }
+        Some(Ttl::Seconds(0)) | Some(Ttl::Blocks(0)) => {
+            return Err(StdError::generic_err("ttl must be greater than zero").into());
+        }
+        Some(Ttl::Seconds(seconds)) => {
+            Expiry::AtTime(env.block.time.seconds().saturating_add(seconds))
+        }
+        Some(Ttl::Blocks(blocks)) => Expiry::AtHeight(env.block.height.saturating_add(blocks)),
+    };
+    EXPIRIES.save(deps.storage, (owner, key), &expiry)?;
+    Ok(())
+}
+
+fn record_version(
+    deps: DepsMut,
+    env: &Env,
+    writer: &Addr,
+    owner: &Addr,
+    key: &str,
+    value: Option<String>,
+    limits: &Limits,
+) -> StdResult<u64> {
+    let version = current_version(deps.as_ref(), owner, key)? + 1;
+
+    ENTRY_VERSIONS.save(
+        deps.storage,
+        (owner, key),
+        &EntryVersion {
+            version,
+            block_height: env.block.height,
+            writer: writer.clone(),
+        },
+    )?;
+    ENTRY_HISTORY.save(
+        deps.storage,
+        (owner, key, version),
+        &ValueVersion {
+            version,
+            value,
+            block_height: env.block.height,
+            writer: writer.clone(),
+        },
+    )?;
+
+    // Only the newest `max_history` versions are kept, also after the limit is lowered
+    let keep_from = (version + 1).saturating_sub(limits.max_history as u64);
+    trim_history(deps.storage, owner, key, keep_from)?;
+
+    Ok(version)
+}
+
+// Expired entries read as missing until PruneExpired removes them
+fn load_value(deps: Deps, env: &Env, owner: &Addr, key: &str) -> StdResult<Option<String>> {
+    if is_expired(deps, env, owner, key) {
+        return Ok(None);
+    }
+    let value = OWNER_DATA.may_load(deps.storage, (owner, key))?;
+    if value.is_none() && key.is_empty() {
+        // Profiles written before namespacing were stored under the owner's address
+        return USER_DATA.may_load(deps.storage, owner.as_str());
+    }
+    Ok(value)
+}
+
+fn record_admin_action(
+    deps: DepsMut,
+    env: &Env,
+    info: &MessageInfo,
+    action: &str,
+    owner: &Addr,
+    key: &str,
+    reason: Option<String>,
+) -> StdResult<u64> {
+    let id = ADMIN_LOG_COUNT.may_load(deps.storage)?.unwrap_or_default() + 1;
+    ADMIN_LOG_COUNT.save(deps.storage, &id)?;
+
+    let entry = AdminAction {
+        id,
+        admin: info.sender.clone(),
+        action: action.to_string(),
+        owner: owner.clone(),
+        key: key.to_string(),
+        reason,
+        block_height: env.block.height,
+    };
+    ADMIN_LOG.save(deps.storage, id, &entry)?;
+
+    Ok(id)
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -138,4 +2427,581 @@ pub enum ContractError {
 
     #[error("Unauthorized")]
     Unauthorized {},
-}
\ No newline at end of file
+
+    #[error("Writes are paused")]
+    Paused {},
+
+    #[error("Key is longer than {max} bytes")]
+    KeyTooLong { max: u32 },
+
+    #[error("Invalid profile: {reason}")]
+    InvalidProfile { reason: String },
+
+    #[error("Storage quota exceeded: {reason}")]
+    QuotaExceeded { reason: String },
+
+    #[error("Version {version} not found")]
+    VersionNotFound { version: u64 },
+
+    #[error("Version conflict: expected {expected}, current is {current}")]
+    VersionConflict { expected: u64, current: u64 },
+
+    #[error("Invalid JSON: {reason}")]
+    InvalidJson { reason: String },
+
+    #[error("Stored value and patch must both be JSON objects")]
+    NotAnObject {},
+
+    #[error("Batch has more than {max} ops")]
+    BatchTooLarge { max: u32 },
+
+    #[error("Invalid grant: {reason}")]
+    InvalidGrant { reason: String },
+
+    #[error("{0}")]
+    Verification(#[from] VerificationError),
+
+    #[error("At most {max} hooks can be registered")]
+    TooManyHooks { max: u32 },
+
+    #[error("Invalid signature")]
+    InvalidSignature {},
+
+    #[error("Invalid nonce: expected {expected}, got {got}")]
+    InvalidNonce { expected: u64, got: u64 },
+
+    #[error("Storage deposit of {required} required, {locked} locked")]
+    InsufficientDeposit { required: Uint128, locked: Uint128 },
+
+    #[error("Storage deposits must be paid in {denom}")]
+    InvalidDeposit { denom: String },
+
+    #[error("Deposits are still locked in {denom}")]
+    DepositsLocked { denom: String },
+
+    #[error("Username {username} is taken")]
+    UsernameTaken { username: String },
+
+    #[error("Invalid username: {reason}")]
+    InvalidUsername { reason: String },
+
+    #[error("Address already has a username")]
+    AlreadyHasUsername {},
+
+    #[error("Cannot send friend requests to or block yourself")]
+    CannotFriendSelf {},
+
+    #[error("Already friends")]
+    AlreadyFriends {},
+
+    #[error("Not friends")]
+    NotFriends {},
+
+    #[error("Friend request not found")]
+    FriendRequestNotFound {},
+
+    #[error("User is blocked")]
+    Blocked {},
+
+    #[error("Cannot migrate from contract {actual}, expected {expected}")]
+    WrongContract { expected: String, actual: String },
+
+    #[error("Cannot downgrade from {from} to {to}")]
+    CannotDowngrade { from: String, to: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{mock_env, mock_info, MockQuerier, MockStorage};
+    use cosmwasm_std::{coins, CosmosMsg, OwnedDeps};
+    use cw_multi_test::addons::MockApiBech32;
+    use k256::ecdsa::{signature::hazmat::PrehashSigner, Signature, SigningKey};
+    use std::marker::PhantomData;
+
+    type TestDeps = OwnedDeps<MockStorage, MockApiBech32, MockQuerier>;
+
+    fn setup() -> (TestDeps, Addr) {
+        let mut deps = OwnedDeps {
+            storage: MockStorage::default(),
+            api: MockApiBech32::new("xion"),
+            querier: MockQuerier::default(),
+            custom_query_type: PhantomData,
+        };
+        let admin = deps.api.addr_make("admin");
+        let msg = InstantiateMsg { admin: None, limits: None };
+        instantiate(deps.as_mut(), mock_env(), mock_info(admin.as_str(), &[]), msg).unwrap();
+        (deps, admin)
+    }
+
+    fn set_value(key: &str, value: &str) -> ExecuteMsg {
+        ExecuteMsg::SetValue {
+            key: key.to_string(),
+            value: value.to_string(),
+            expected_version: None,
+            owner: None,
+            ttl: None,
+        }
+    }
+
+    fn delete_value(key: &str) -> ExecuteMsg {
+        ExecuteMsg::DeleteValue { key: key.to_string(), expected_version: None, owner: None }
+    }
+
+    fn enable_deposits(deps: &mut TestDeps, admin: &Addr, price_per_byte: u128) {
+        let msg = ExecuteMsg::UpdateConfig {
+            paused: None,
+            limits: None,
+            deposit: Some(DepositConfig {
+                denom: "uxion".to_string(),
+                price_per_byte: Uint128::new(price_per_byte),
+            }),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(admin.as_str(), &[]), msg).unwrap();
+    }
+
+    fn query_deposit(deps: &TestDeps, address: &Addr) -> DepositResponse {
+        let msg = QueryMsg::Deposit { address: address.to_string() };
+        from_json(query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap()
+    }
+
+    fn refund_of(response: &Response) -> Option<(String, u128)> {
+        response.messages.iter().find_map(|sub| match &sub.msg {
+            CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
+                Some((to_address.clone(), amount[0].amount.u128()))
+            }
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn deposit_is_charged_for_growth_and_refunded_on_delete() {
+        let (mut deps, admin) = setup();
+        enable_deposits(&mut deps, &admin, 2);
+        let alice = deps.api.addr_make("alice");
+
+        // 5 bytes at 2 per byte needs 10
+        let err = execute(
+            deps.as_mut(), mock_env(), mock_info(alice.as_str(), &coins(9, "uxion")),
+            set_value("k", "hello"),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::InsufficientDeposit { required, locked }
+                if required.u128() == 10 && locked.u128() == 9
+        ));
+
+        let err = execute(
+            deps.as_mut(), mock_env(), mock_info(alice.as_str(), &coins(10, "ujunk")),
+            set_value("k", "hello"),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::InvalidDeposit { denom } if denom == "uxion"));
+
+        // Overpaying locks the requirement and refunds the rest
+        let res = execute(
+            deps.as_mut(), mock_env(), mock_info(alice.as_str(), &coins(15, "uxion")),
+            set_value("k", "hello"),
+        )
+        .unwrap();
+        assert_eq!(refund_of(&res), Some((alice.to_string(), 5)));
+        assert_eq!(query_deposit(&deps, &alice).locked, Uint128::new(10));
+
+        // Shrinking refunds the freed bytes
+        let res = execute(
+            deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), set_value("k", "hi"),
+        )
+        .unwrap();
+        assert_eq!(refund_of(&res), Some((alice.to_string(), 6)));
+
+        let res = execute(
+            deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), delete_value("k"),
+        )
+        .unwrap();
+        assert_eq!(refund_of(&res), Some((alice.to_string(), 4)));
+        assert_eq!(query_deposit(&deps, &alice).locked, Uint128::zero());
+    }
+
+    #[test]
+    fn price_increase_does_not_block_deletes_or_shrinking_writes() {
+        let (mut deps, admin) = setup();
+        enable_deposits(&mut deps, &admin, 1);
+        let alice = deps.api.addr_make("alice");
+        for (key, value) in [("a", "1234"), ("b", "5678")] {
+            execute(
+                deps.as_mut(), mock_env(), mock_info(alice.as_str(), &coins(4, "uxion")),
+                set_value(key, value),
+            )
+            .unwrap();
+        }
+
+        // Alice is now under-collateralised but can still free space without paying
+        enable_deposits(&mut deps, &admin, 10);
+        let res = execute(
+            deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), set_value("a", "12"),
+        )
+        .unwrap();
+        assert_eq!(refund_of(&res), None);
+        execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), delete_value("b"))
+            .unwrap();
+
+        // Growing again has to cover the new price
+        let err = execute(
+            deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), set_value("a", "123"),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::InsufficientDeposit { required, locked }
+                if required.u128() == 30 && locked.u128() == 8
+        ));
+    }
+
+    #[test]
+    fn deposit_denom_is_locked_while_deposits_are_held() {
+        let (mut deps, admin) = setup();
+        enable_deposits(&mut deps, &admin, 1);
+        let alice = deps.api.addr_make("alice");
+        execute(
+            deps.as_mut(), mock_env(), mock_info(alice.as_str(), &coins(3, "uxion")),
+            set_value("k", "abc"),
+        )
+        .unwrap();
+
+        let msg = ExecuteMsg::UpdateConfig {
+            paused: None,
+            limits: None,
+            deposit: Some(DepositConfig {
+                denom: "uother".to_string(),
+                price_per_byte: Uint128::one(),
+            }),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(admin.as_str(), &[]), msg.clone())
+            .unwrap_err();
+        assert!(matches!(err, ContractError::DepositsLocked { denom } if denom == "uxion"));
+
+        execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), delete_value("k"))
+            .unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info(admin.as_str(), &[]), msg).unwrap();
+    }
+
+    fn signed_write(key: &SigningKey, value: &str, nonce: u64) -> ExecuteMsg {
+        let env = mock_env();
+        let payload = SignedWrite {
+            chain_id: env.block.chain_id,
+            contract: env.contract.address.to_string(),
+            key: "k".to_string(),
+            value: value.to_string(),
+            nonce,
+        };
+        let hash = Sha256::digest(to_json_vec(&payload).unwrap());
+        let signature: Signature = key.sign_prehash(&hash).unwrap();
+        ExecuteMsg::SetValueSigned {
+            key: "k".to_string(),
+            value: value.to_string(),
+            nonce,
+            pubkey: Binary::from(key.verifying_key().to_encoded_point(true).as_bytes()),
+            signature: Binary::from(signature.to_bytes().to_vec()),
+        }
+    }
+
+    #[test]
+    fn signed_write_is_stored_under_the_signer() {
+        let (mut deps, _) = setup();
+        let key = SigningKey::from_bytes(&[7u8; 32].into()).unwrap();
+        let relayer = deps.api.addr_make("relayer");
+
+        let res = execute(
+            deps.as_mut(), mock_env(), mock_info(relayer.as_str(), &[]),
+            signed_write(&key, "hello", 0),
+        )
+        .unwrap();
+        let owner = res.attributes.iter().find(|attr| attr.key == "owner").unwrap().value.clone();
+        assert_ne!(owner, relayer.to_string());
+
+        let msg = QueryMsg::GetValue { key: "k".to_string(), owner: Some(owner.clone()) };
+        let stored = query(deps.as_ref(), mock_env(), msg).unwrap();
+        assert!(String::from_utf8(stored.to_vec()).unwrap().contains("hello"));
+
+        let msg = QueryMsg::Nonce { address: owner };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        assert_eq!(from_json::<NonceResponse>(res).unwrap().nonce, 1);
+    }
+
+    #[test]
+    fn signed_write_rejects_replays_and_tampering() {
+        let (mut deps, _) = setup();
+        let key = SigningKey::from_bytes(&[7u8; 32].into()).unwrap();
+        let relayer = mock_info(deps.api.addr_make("relayer").as_str(), &[]);
+
+        let first = signed_write(&key, "hello", 0);
+        execute(deps.as_mut(), mock_env(), relayer.clone(), first.clone()).unwrap();
+        let err = execute(deps.as_mut(), mock_env(), relayer.clone(), first).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidNonce { expected: 1, got: 0 }));
+
+        // A signature over one value can't be replayed with another
+        let ExecuteMsg::SetValueSigned { key: k, nonce, pubkey, signature, .. } =
+            signed_write(&key, "hello", 1)
+        else {
+            unreachable!()
+        };
+        let tampered = ExecuteMsg::SetValueSigned {
+            key: k, value: "evil".to_string(), nonce, pubkey, signature,
+        };
+        let err = execute(deps.as_mut(), mock_env(), relayer.clone(), tampered).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidSignature {}));
+
+        // Nor on another chain
+        let mut env = mock_env();
+        env.block.chain_id = "other-chain".to_string();
+        let err = execute(deps.as_mut(), env, relayer, signed_write(&key, "hello", 1)).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidSignature {}));
+    }
+
+    #[test]
+    fn owner_page_cursor_before_the_prefix_starts_at_the_prefix() {
+        let (mut deps, _) = setup();
+        let alice = deps.api.addr_make("alice");
+        for key in ["a", "pet/1", "pet/2", "z"] {
+            execute(deps.as_mut(), mock_env(), mock_info(alice.as_str(), &[]), set_value(key, "v"))
+                .unwrap();
+        }
+
+        let page = |deps: &TestDeps, owner: &Addr| {
+            let msg = QueryMsg::GetAllValues {
+                owner: Some(alice.to_string()),
+                key_prefix: Some("pet/".to_string()),
+                start_after: Some(ValueCursor { owner: owner.to_string(), key: "a".to_string() }),
+                limit: None,
+            };
+            query(deps.as_ref(), mock_env(), msg)
+        };
+        let res: AllValuesResponse = from_json(page(&deps, &alice).unwrap()).unwrap();
+        let keys: Vec<_> = res.values.iter().map(|entry| entry.key.as_str()).collect();
+        assert_eq!(keys, ["pet/1", "pet/2"]);
+
+        let bob = deps.api.addr_make("bob");
+        assert!(page(&deps, &bob).is_err());
+    }
+
+    fn history(deps: &TestDeps, owner: &Addr, key: &str) -> Vec<ValueVersion> {
+        let (owner, key) = (owner.to_string(), key.to_string());
+        let msg = QueryMsg::ValueHistory { owner, key, limit: None };
+        from_json(query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn takedown_cannot_be_reverted() {
+        let (mut deps, admin) = setup();
+        let alice = deps.api.addr_make("alice");
+        let info = mock_info(alice.as_str(), &[]);
+        execute(deps.as_mut(), mock_env(), info.clone(), set_value("bio", "abusive")).unwrap();
+
+        let msg = ExecuteMsg::TakedownValue {
+            owner: alice.to_string(),
+            key: "bio".to_string(),
+            reason: "abuse".to_string(),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(admin.as_str(), &[]), msg).unwrap();
+
+        let revert = ExecuteMsg::RevertValue { key: "bio".to_string(), version: 1 };
+        let err = execute(deps.as_mut(), mock_env(), info, revert).unwrap_err();
+        assert!(matches!(err, ContractError::VersionNotFound { version: 1 }));
+        let versions = history(&deps, &alice, "bio");
+        assert!(versions.iter().all(|version| version.value.is_none()));
+    }
+
+    #[test]
+    fn history_is_bounded_by_live_keys_and_the_current_limit() {
+        let (mut deps, admin) = setup();
+        let alice = deps.api.addr_make("alice");
+        let info = mock_info(alice.as_str(), &[]);
+        for _ in 0..5 {
+            for key in ["a", "b"] {
+                execute(deps.as_mut(), mock_env(), info.clone(), set_value(key, "x")).unwrap();
+            }
+            execute(deps.as_mut(), mock_env(), info.clone(), delete_value("b")).unwrap();
+        }
+        // Only the delete marker is left for a deleted key
+        assert_eq!(history(&deps, &alice, "b").len(), 1);
+        assert_eq!(history(&deps, &alice, "a").len(), 5);
+
+        let msg = ExecuteMsg::UpdateConfig {
+            paused: None,
+            limits: Some(Limits { max_history: 2, ..Limits::default() }),
+            deposit: None,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(admin.as_str(), &[]), msg).unwrap();
+        execute(deps.as_mut(), mock_env(), info, set_value("a", "y")).unwrap();
+        let versions: Vec<u64> = history(&deps, &alice, "a").iter().map(|v| v.version).collect();
+        assert_eq!(versions, [6, 5]);
+    }
+
+    // State as left by the original lib.rs contract: cw2 info and address-keyed profiles
+    fn legacy_deps(profiles: &[(&str, &str)]) -> TestDeps {
+        let mut deps = OwnedDeps {
+            storage: MockStorage::default(),
+            api: MockApiBech32::new("xion"),
+            querier: MockQuerier::default(),
+            custom_query_type: PhantomData,
+        };
+        set_contract_version(&mut deps.storage, CONTRACT_NAME, "1.0.0").unwrap();
+        for (key, value) in profiles {
+            USER_DATA.save(&mut deps.storage, key, &value.to_string()).unwrap();
+        }
+        deps
+    }
+
+    fn migrate_msg(admin: Option<&Addr>, limit: u32, start_after: Option<String>) -> MigrateMsg {
+        MigrateMsg {
+            admin: admin.map(|admin| admin.to_string()),
+            limits: None,
+            legacy_profiles_limit: Some(limit),
+            legacy_start_after: start_after,
+        }
+    }
+
+    fn profile_version(deps: &TestDeps, owner: &str) -> u64 {
+        let msg = QueryMsg::GetValue { key: String::new(), owner: Some(owner.to_string()) };
+        from_json::<ValueResponse>(query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap().version
+    }
+
+    #[test]
+    fn migrate_moves_lib_rs_profiles_in_resumable_pages() {
+        let api = MockApiBech32::new("xion");
+        let mut owners: Vec<String> =
+            ["alice", "bob", "carol"].iter().map(|name| api.addr_make(name).to_string()).collect();
+        owners.sort();
+        let oversize = "x".repeat(DEFAULT_MAX_VALUE_SIZE as usize + 1);
+        let mut deps = legacy_deps(&[
+            (owners[0].as_str(), "{}"),
+            (owners[1].as_str(), oversize.as_str()),
+            (owners[2].as_str(), "{}"),
+            ("not-an-address", "{}"),
+        ]);
+        let admin = api.addr_make("admin");
+
+        // "not-an-address" sorts first, so the first page stops at the oversize profile
+        let res = migrate(deps.as_mut(), mock_env(), migrate_msg(Some(&admin), 3, None)).unwrap();
+        let attr = |res: &Response, key: &str| {
+            res.attributes.iter().find(|attr| attr.key == key).unwrap().value.clone()
+        };
+        assert_eq!(attr(&res, "legacy_profiles_moved"), "1");
+        assert_eq!(attr(&res, "legacy_profiles_skipped"), format!("not-an-address,{}", owners[1]));
+        assert_eq!(attr(&res, "legacy_last_key"), owners[1]);
+        assert_eq!(get_contract_version(&deps.storage).unwrap().version, CONTRACT_VERSION);
+
+        let next = Some(attr(&res, "legacy_last_key"));
+        let res = migrate(deps.as_mut(), mock_env(), migrate_msg(None, 3, next)).unwrap();
+        assert_eq!(attr(&res, "legacy_profiles_moved"), "1");
+        assert_eq!(attr(&res, "legacy_last_key"), "");
+
+        assert_eq!(profile_version(&deps, &owners[0]), 1);
+        assert_eq!(profile_version(&deps, &owners[1]), 0);
+        assert_eq!(profile_version(&deps, &owners[2]), 1);
+        // Skipped entries stay behind for MigrateLegacyValue
+        assert!(USER_DATA.has(&deps.storage, "not-an-address"));
+        assert!(USER_DATA.has(&deps.storage, &owners[1]));
+        assert!(!USER_DATA.has(&deps.storage, &owners[0]));
+    }
+
+    #[test]
+    fn migrate_checks_contract_name_version_and_admin() {
+        let admin = MockApiBech32::new("xion").addr_make("admin");
+
+        let mut deps = legacy_deps(&[]);
+        let err = migrate(deps.as_mut(), mock_env(), migrate_msg(None, 0, None)).unwrap_err();
+        assert!(matches!(err, ContractError::Std(StdError::GenericErr { .. })));
+
+        set_contract_version(&mut deps.storage, "other-contract", "1.0.0").unwrap();
+        let err = migrate(deps.as_mut(), mock_env(), migrate_msg(Some(&admin), 0, None))
+            .unwrap_err();
+        assert!(matches!(err, ContractError::WrongContract { .. }));
+
+        set_contract_version(&mut deps.storage, CONTRACT_NAME, "9.0.0").unwrap();
+        let err = migrate(deps.as_mut(), mock_env(), migrate_msg(Some(&admin), 0, None))
+            .unwrap_err();
+        assert!(matches!(err, ContractError::CannotDowngrade { .. }));
+    }
+
+    #[test]
+    fn admin_writes_and_takedowns_notify_hooks() {
+        let (mut deps, admin) = setup();
+        let alice = deps.api.addr_make("alice");
+        let admin_info = mock_info(admin.as_str(), &[]);
+        let hook = deps.api.addr_make("hook");
+        let msg = ExecuteMsg::AddHook { address: hook.to_string() };
+        execute(deps.as_mut(), mock_env(), admin_info.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::AdminSetValue {
+            owner: alice.to_string(),
+            key: "bio".to_string(),
+            value: "hello".to_string(),
+        };
+        let res = execute(deps.as_mut(), mock_env(), admin_info.clone(), msg).unwrap();
+        assert_eq!(res.messages.len(), 1);
+
+        let msg = ExecuteMsg::TakedownValue {
+            owner: alice.to_string(),
+            key: "bio".to_string(),
+            reason: "abuse".to_string(),
+        };
+        let res = execute(deps.as_mut(), mock_env(), admin_info, msg).unwrap();
+        let CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, msg, .. }) = &res.messages[0].msg
+        else {
+            panic!("expected a hook call");
+        };
+        assert_eq!(contract_addr, hook.as_str());
+        let HookMsg::UserMapChanged { owner, key, old_hash, new_hash } = from_json(msg).unwrap();
+        assert_eq!((owner, key), (alice.to_string(), "bio".to_string()));
+        assert_eq!(old_hash, value_hash(Some("hello")));
+        assert_eq!(new_hash, None);
+    }
+
+    #[test]
+    fn exported_entries_import_with_versions_writers_and_expiry() {
+        let (mut source, _) = setup();
+        let alice = source.api.addr_make("alice");
+        let info = mock_info(alice.as_str(), &[]);
+        for value in ["one", "two", "three"] {
+            execute(source.as_mut(), mock_env(), info.clone(), set_value("bio", value)).unwrap();
+        }
+        let msg = ExecuteMsg::SetValue {
+            key: "status".to_string(),
+            value: "away".to_string(),
+            expected_version: None,
+            owner: None,
+            ttl: Some(Ttl::Blocks(100)),
+        };
+        execute(source.as_mut(), mock_env(), info, msg).unwrap();
+
+        let export = |deps: &TestDeps| -> ExportResponse {
+            let msg = QueryMsg::ExportEntries { start_after: None, limit: None };
+            from_json(query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap()
+        };
+        let exported = export(&source);
+        assert_eq!(exported.entries.len(), 2);
+
+        // Exported JSON is passed back in unchanged
+        let (mut target, admin) = setup();
+        let entries: Vec<ImportEntry> =
+            from_json(to_json_binary(&exported.entries).unwrap()).unwrap();
+        let msg = ExecuteMsg::ImportEntries { entries, skip_existing: false };
+        execute(target.as_mut(), mock_env(), mock_info(admin.as_str(), &[]), msg).unwrap();
+
+        let imported = export(&target);
+        assert_eq!(imported.entries, exported.entries);
+        let bio = &imported.entries[0];
+        assert_eq!(bio.key, "bio");
+        let version = bio.version.as_ref().unwrap();
+        assert_eq!((version.version, &version.writer), (3, &alice));
+        let expires = Expiry::AtHeight(mock_env().block.height + 100);
+        assert_eq!(imported.entries[1].expiry, Some(expires));
+
+        // Numbering continues from the exported version
+        let info = mock_info(alice.as_str(), &[]);
+        let res = execute(target.as_mut(), mock_env(), info, set_value("bio", "four")).unwrap();
+        assert!(res.attributes.iter().any(|attr| attr.key == "version" && attr.value == "4"));
+    }
+}